@@ -1,84 +1,709 @@
 extern crate docopt;
-extern crate rustc_serialize;
 extern crate clink;
+extern crate semver;
 
-use std::process;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
 use std::io::{self, Write};
-use docopt::Docopt;
-use clink::{Project, ClinkError};
+use docopt::{ArgvMap, Docopt};
+use semver::VersionReq;
+use clink::{Project, ProjectClass, ProjectGraph, Workspace, FeatureSelection, NativeBuilder, Profile, ClinkError, TargetKind};
+use clink::{FileStatus, Generator, Platform, Severity, VisualStudioGenerator, check_manifests, find_project_path};
+use clink::{render_dot, render_inverted_tree, render_metadata, render_tree};
+use clink::{Dependency, DependencySource, GitReference, GitSource, add_dependency, remove_dependency};
+use clink::{NewProject, compare_files, find_template, init_project, new_project, unified_diff};
 
 const USAGE: &'static str = "
 A simple C++ build system generator
 
 Usage:
-    clink [<command> [<args>...]]
+    clink [options] [<command> [<args>...]]
+    clink (-h | --help)
+
+Options:
+    --manifest-path <path>  Path to the Clink.toml of the project to use
+    -C <dir>                Change to this directory before doing anything
 
 Some common clink commands are:
-    generate    Generate Visual Studio files for the current project (default)
+    generate    Generate Visual Studio files for the current project or workspace (default)
     filters     Generate just the .vcxproj.fiters file for the current project
+    build       Compile the current project and its dependencies
+    run         Build and run an application in the current project
+    test        Build and run all tests in the current project
+    tree        Show the dependency graph of the current project
+    metadata    Print the resolved dependency graph for other tools to read
+    add         Add a dependency to the current project's Clink.toml
+    remove      Remove a dependency from the current project's Clink.toml
+    check       Check the manifests of the current project and its dependencies for mistakes
     init        Create a new clink project in the current directory
+    new         Create a new clink project in a new directory
 ";
 
+const GENERATE_USAGE: &str = "
+Generate Visual Studio files for the current project or workspace
 
-#[derive(Debug, RustcDecodable)]
-pub struct Flags {
-    arg_command: Option<String>,
-    arg_args: Vec<String>,
-}
+Usage:
+    clink generate [options]
+
+Options:
+    --dry-run           List the files that would be created or changed, without writing them
+    --diff              Show what would change in every file, without writing them
+    --check             Fail if any generated file is missing or out of date, without writing them
+    --locked            Fail instead of updating Clink.lock
+    --features <names>  Comma separated list of features to enable
+    --all-features      Enable all features of the current project
+";
+
+const FILTERS_USAGE: &str = "
+Generate just the .vcxproj.filters file for the current project
+
+Usage:
+    clink filters
+";
+
+const BUILD_USAGE: &str = "
+Compile the current project and its dependencies into target/<profile>/
+
+Usage:
+    clink build [options]
+
+Options:
+    --release           Build with optimizations, into target/release/
+    -j, --jobs <n>      Amount of compilers to run in parallel
+    --locked            Fail instead of updating Clink.lock
+    --features <names>  Comma separated list of features to enable
+    --all-features      Enable all features of the current project
+";
+
+const RUN_USAGE: &str = "
+Build and run an application in the current project
+
+Usage:
+    clink run [options] [--] [<args>...]
+
+Options:
+    --bin <name>        Name of the application to run, defaults to the current project
+    --release           Build with optimizations, into target/release/
+    -j, --jobs <n>      Amount of compilers to run in parallel
+    --locked            Fail instead of updating Clink.lock
+    --features <names>  Comma separated list of features to enable
+    --all-features      Enable all features of the current project
+";
+
+const TEST_USAGE: &str = "
+Build and run all test projects in the current project's dependency graph
+
+Usage:
+    clink test [options]
+
+Options:
+    --release           Build with optimizations, into target/release/
+    -j, --jobs <n>      Amount of compilers to run in parallel
+    --locked            Fail instead of updating Clink.lock
+    --features <names>  Comma separated list of features to enable
+    --all-features      Enable all features of the current project
+";
+
+const TREE_USAGE: &str = "
+Show the dependency graph of the current project or workspace, projects that were already shown
+are marked with (*)
+
+Usage:
+    clink tree [options]
+
+Options:
+    --format <format>    Output format, text or dot for Graphviz [default: text]
+    -i, --invert <name>  Show the projects depending on a project instead
+    --locked             Fail if Clink.lock is missing or out of date
+    --features <names>   Comma separated list of features to enable
+    --all-features       Enable all features of the current project
+";
+
+const METADATA_USAGE: &str = "
+Print the fully resolved dependency graph of the current project or workspace, for other tools
+to read. The output has a format_version field that changes when the format does.
+
+Usage:
+    clink metadata [options]
+
+Options:
+    --format <format>    Output format, only json is supported [default: json]
+    --locked             Fail if Clink.lock is missing or out of date
+    --features <names>   Comma separated list of features to enable
+    --all-features       Enable all features of the current project
+";
+
+const ADD_USAGE: &str = "
+Add a dependency to the current project's Clink.toml, or replace it if it's already there
+
+Usage:
+    clink add <name> --path <path> [options]
+    clink add <name> --git <url> [--branch <branch> | --tag <tag> | --rev <rev>] [options]
+
+Options:
+    --path <path>          Folder of the dependency's project
+    --git <url>            Git repository with the dependency's project at its root
+    --branch <branch>      Branch of the git repository to use
+    --tag <tag>            Tag of the git repository to use
+    --rev <rev>            Commit of the git repository to use
+    --version <req>        Versions of the dependency's project to accept, such as ^1.2
+    --package <name>       Name of the dependency's project, if it's different from <name>
+    --features <names>     Comma separated list of features to enable in the dependency
+    --no-default-features  Don't enable the dependency's default feature
+    --optional             Only use the dependency when a feature enables it
+    --public               Make the dependency's include folder available to dependents
+    --dev                  Add it to [dev-dependencies] instead
+";
+
+const REMOVE_USAGE: &str = "
+Remove a dependency from the current project's Clink.toml
+
+Usage:
+    clink remove <name> [options]
+
+Options:
+    --dev  Remove it from [dev-dependencies] instead
+";
+
+const CHECK_USAGE: &str = "
+Check every Clink.toml in the current project or workspace's dependency graph for mistakes, such
+as unknown keys, missing dependencies and dependency cycles, without writing anything
+
+Usage:
+    clink check
+";
+
+const INIT_USAGE: &str = "
+Create a new clink project in the current directory
+
+Usage:
+    clink init [options]
+
+Options:
+    --name <name>      Name of the project, defaults to the directory's name
+    --type <type>      Type of project, application, library or test [default: library]
+    --template <dir>   Template to copy the project's files from, either a directory or the name
+                       of one in ~/.clink/templates
+    --force            Overwrite an existing Clink.toml
+";
+
+const NEW_USAGE: &str = "
+Create a new clink project in a new directory
+
+Usage:
+    clink new <dir> [options]
+
+Options:
+    --name <name>      Name of the project, defaults to the directory's name
+    --type <type>      Type of project, application, library or test [default: library]
+    --template <dir>   Template to copy the project's files from, either a directory or the name
+                       of one in ~/.clink/templates
+";
 
 fn main() {
-    let args: Flags = Docopt::new(USAGE)
-        .and_then(|d| d.decode())
+    let args = Docopt::new(USAGE)
+        .and_then(|d| d.options_first(true).parse())
         .unwrap_or_else(|e| e.exit());
 
-    let command = args.arg_command.unwrap_or("generate".into());
-    let command_func: fn() -> Result<(), ClinkError> = match command.as_ref() {
+    // Change directory first, everything else is relative to it
+    let dir = args.get_str("-C");
+    if !dir.is_empty() {
+        env::set_current_dir(dir).unwrap_or_else(|e| {
+            eprintln!("Error: Could not change to directory \"{}\": {}", dir, e);
+            process::exit(1);
+        });
+    }
+
+    let config = Config {
+        manifest_path: match args.get_str("--manifest-path") {
+            "" => None,
+            path => Some(PathBuf::from(path)),
+        },
+    };
+
+    let command = match args.get_str("<command>") {
+        "" => "generate",
+        command => command,
+    };
+    let command_func: fn(Vec<String>, &Config) -> Result<(), ClinkError> = match command {
         "generate" => try_generate,
         "filters" => try_filters,
+        "build" => try_build,
+        "run" => try_run,
+        "test" => try_test,
+        "tree" => try_tree,
+        "metadata" => try_metadata,
+        "add" => try_add,
+        "remove" => try_remove,
+        "check" => try_check,
         "init" => try_init,
+        "new" => try_new,
         _ => {
             write!(io::stderr(), "Error: Unknown command \"{}\"\n", command).unwrap();
             process::exit(1);
         }
     };
 
-    command_func().unwrap_or_else(|e| {
+    // Pass the command and its arguments on, the command parses them itself
+    let mut argv = vec!("clink".to_string(), command.to_string());
+    argv.extend(args.get_vec("<args>").iter().map(|a| a.to_string()));
+
+    // Docopt drops the first "--", put it back so run can pass flags on to the application
+    let raw: Vec<String> = env::args().collect();
+    if let Some(index) = raw.iter().position(|a| a == "--") {
+        let after = raw.len() - index - 1;
+        if after <= argv.len() - 2 {
+            let at = argv.len() - after;
+            argv.insert(at, "--".into());
+        }
+    }
+
+    command_func(argv, &config).unwrap_or_else(|e| {
         write!(io::stderr(), "Error: {}\n", e).unwrap();
         process::exit(1);
     });
 }
 
-fn try_generate() -> Result<(), ClinkError> {
-    let proj = try!(Project::open("./"));
-    try!(proj.generate_sln());
+/// Options that apply to every command.
+struct Config {
+    manifest_path: Option<PathBuf>,
+}
+
+impl Config {
+    /// Get the folder to start looking for a project in.
+    fn start_path(&self) -> Result<PathBuf, ClinkError> {
+        match self.manifest_path {
+            Some(ref path) => {
+                if path.file_name().map(|n| n != "Clink.toml").unwrap_or(true) || !path.is_file() {
+                    return Err(ClinkError::InvalidProjectStructure(
+                        path.clone(), "The manifest path must be the path to a Clink.toml file".into()
+                    ));
+                }
+
+                let parent = path.parent().unwrap();
+                Ok(if parent.as_os_str().is_empty() { PathBuf::from("./") } else { parent.to_path_buf() })
+            },
+            None => Ok(PathBuf::from("./")),
+        }
+    }
+
+    /// Open the workspace the current project is in, or just the project if it's in none.
+    fn workspace(&self) -> Result<Workspace, ClinkError> {
+        Workspace::discover(try!(self.start_path()))
+    }
+
+    /// Open the nearest project, ignoring any workspace it's in.
+    fn project(&self) -> Result<Project, ClinkError> {
+        Project::open(try!(find_project_path(try!(self.start_path()))))
+    }
+}
+
+fn parse_args(usage: &str, argv: Vec<String>) -> ArgvMap {
+    Docopt::new(usage)
+        .and_then(|d| d.argv(argv).parse())
+        .unwrap_or_else(|e| e.exit())
+}
+
+fn try_generate(argv: Vec<String>, config: &Config) -> Result<(), ClinkError> {
+    let args = parse_args(GENERATE_USAGE, argv);
+    let dry_run = args.get_bool("--dry-run");
+    let diff = args.get_bool("--diff");
+    let check = args.get_bool("--check");
+
+    let workspace = try!(config.workspace());
+    if !dry_run && !diff && !check {
+        let graph = try!(resolve(&workspace, &args));
+        return VisualStudioGenerator::new().generate(&graph);
+    }
+
+    // Nothing gets written, not even Clink.lock, it's compared like the generated files instead
+    let graph = try!(ProjectGraph::resolve_workspace(&workspace, &feature_args(&args)));
+    report_patches(&workspace, &graph);
+    let mut files = try!(VisualStudioGenerator::new().generate_files(&graph));
+    if try!(workspace.lockfile_changed(&graph)) {
+        files.insert(workspace.lock_path(), graph.lockfile().to_string());
+    }
+
+    let comparisons = try!(compare_files(&files));
+    for comparison in &comparisons {
+        let name = comparison.path.strip_prefix(workspace.path()).unwrap_or(comparison.path).display().to_string();
+        if dry_run {
+            let status = match comparison.status {
+                FileStatus::Created => "Create",
+                FileStatus::Changed => "Change",
+                FileStatus::Unchanged => "Unchanged",
+            };
+            println!("{:>12} {}", status, name);
+        }
+        if diff && comparison.status != FileStatus::Unchanged {
+            let old_name = if comparison.current.is_some() { format!("a/{}", name) } else { "/dev/null".into() };
+            print!("{}", unified_diff(
+                comparison.current.as_deref().unwrap_or(""), comparison.generated, &old_name, &format!("b/{}", name)
+            ));
+        }
+    }
+
+    if dry_run {
+        let count = |status| comparisons.iter().filter(|c| c.status == status).count();
+        println!("{:>12} {} to create, {} to change, {} unchanged, nothing was written",
+            "Summary", count(FileStatus::Created), count(FileStatus::Changed), count(FileStatus::Unchanged)
+        );
+    }
+
+    if check {
+        let stale: Vec<_> = comparisons.iter().filter(|c| c.status != FileStatus::Unchanged).collect();
+        for comparison in &stale {
+            let status = if comparison.status == FileStatus::Created { "Missing" } else { "Stale" };
+            eprintln!("{:>12} {}", status, comparison.path.display());
+        }
+
+        if !stale.is_empty() {
+            return Err(ClinkError::InvalidProjectStructure(workspace.path().clone(), format!(
+                "{} generated file(s) are out of date, run clink generate to update them", stale.len()
+            )));
+        }
+        println!("{:>12} {} generated file(s), all up to date", "Checked", comparisons.len());
+    }
 
     Ok(())
 }
 
-fn try_filters() -> Result<(), ClinkError> {
-    let proj = try!(Project::open("./"));
-    proj.generate_vcxproj_filters();
+fn try_filters(argv: Vec<String>, config: &Config) -> Result<(), ClinkError> {
+    parse_args(FILTERS_USAGE, argv);
+
+    let proj = try!(config.project());
+    proj.generate_vcxproj_filters()
+}
+
+fn try_build(argv: Vec<String>, config: &Config) -> Result<(), ClinkError> {
+    let args = parse_args(BUILD_USAGE, argv);
+
+    let workspace = try!(config.workspace());
+    let graph = try!(resolve(&workspace, &args));
+    try!(native_builder(&args).build(&graph));
+
+    Ok(())
+}
+
+fn try_run(argv: Vec<String>, config: &Config) -> Result<(), ClinkError> {
+    let args = parse_args(RUN_USAGE, argv);
+
+    let workspace = try!(config.workspace());
+    let graph = try!(resolve(&workspace, &args)).for_platform(&Platform::host());
+
+    // Find the application to run, by default this is the current project
+    let name = match args.get_str("--bin") {
+        "" => try!(default_application(&workspace)),
+        name => name.to_string(),
+    };
+    let app = try!(graph.find(&name)
+        .filter(|p| p.class.is_executable())
+        .ok_or_else(|| ClinkError::InvalidProjectFile(
+            format!("\"{}\" is not an application in this project's dependency graph", name)
+        ))
+    );
+
+    let builder = native_builder(&args);
+    try!(builder.build(&graph));
+
+    // Run the application, passing on the exit code
+    let path = builder.artifact_path(&graph, app);
+    println!("{:>12} {}", "Running", path.display());
+    let status = try!(Command::new(&path).args(args.get_vec("<args>")).status().map_err(|e|
+        ClinkError::BuildFailed(format!("Could not run {}: {}", path.display(), e))
+    ));
+    process::exit(status.code().unwrap_or(1));
+}
+
+/// Find the only application of the workspace's members, counting both members that are
+/// applications and their [[bin]] targets.
+fn default_application(workspace: &Workspace) -> Result<String, ClinkError> {
+    let mut applications = Vec::new();
+    for member in workspace.members() {
+        if member.class().is_executable() {
+            applications.push(member.name().clone());
+        }
+        applications.extend(member.targets().iter()
+            .filter(|t| t.kind == TargetKind::Bin)
+            .map(|t| t.name.clone())
+        );
+    }
+
+    match applications.len() {
+        1 => Ok(applications.remove(0)),
+        0 => Err(ClinkError::InvalidProjectFile(
+            "There is no application to run, add a [[bin]] target or specify one with --bin".into()
+        )),
+        _ => Err(ClinkError::InvalidProjectFile(format!(
+            "There are several applications, specify one with --bin: {}", applications.join(", ")
+        ))),
+    }
+}
+
+fn try_test(argv: Vec<String>, config: &Config) -> Result<(), ClinkError> {
+    let args = parse_args(TEST_USAGE, argv);
+
+    let workspace = try!(config.workspace());
+    let graph = try!(resolve(&workspace, &args)).for_platform(&Platform::host());
+
+    let builder = native_builder(&args);
+    try!(builder.build(&graph));
+
+    // Run every test, from the directory of the project it's in
+    let mut failed = Vec::new();
+    let tests: Vec<_> = graph.projects().iter().filter(|p| p.class == ProjectClass::Test).collect();
+    for test in &tests {
+        let path = builder.artifact_path(&graph, test);
+        println!("{:>12} {}", "Running", test.name);
+
+        let passed = Command::new(&path).current_dir(&test.path).status()
+            .map(|s| s.success())
+            .unwrap_or(false);
+        if passed {
+            println!("{} ... ok", test.name);
+        } else {
+            println!("{} ... FAILED", test.name);
+            failed.push(test.name.clone());
+        }
+    }
+
+    // Report the results, failing if any test did
+    println!("\ntest result: {}. {} passed; {} failed",
+        if failed.is_empty() { "ok" } else { "FAILED" }, tests.len() - failed.len(), failed.len()
+    );
+    if !failed.is_empty() {
+        println!("\nfailures:");
+        for name in &failed {
+            println!("    {}", name);
+        }
+        process::exit(1);
+    }
 
     Ok(())
 }
 
-fn try_init() -> Result<(), ClinkError> {
-    // TODO: Verify the project doesn't already exist
+fn resolve(workspace: &Workspace, args: &ArgvMap) -> Result<ProjectGraph, ClinkError> {
+    let graph = try!(workspace.resolve_with(&feature_args(args), args.get_bool("--locked")));
+    report_patches(workspace, &graph);
+
+    Ok(graph)
+}
+
+/// Resolve for commands that only show the graph, these leave Clink.lock alone.
+fn resolve_read_only(workspace: &Workspace, args: &ArgvMap) -> Result<ProjectGraph, ClinkError> {
+    let graph = try!(workspace.resolve_read_only(&feature_args(args), args.get_bool("--locked")));
+    report_patches(workspace, &graph);
+
+    Ok(graph)
+}
+
+fn feature_args(args: &ArgvMap) -> FeatureSelection {
+    let mut features = FeatureSelection::new();
+    features.features = split_list(args.get_str("--features"));
+    features.all_features = args.get_bool("--all-features");
+    features
+}
+
+/// Let the user know when dependencies don't come from where their projects say, on stderr so it
+/// doesn't end up in output other tools read.
+fn report_patches(workspace: &Workspace, graph: &ProjectGraph) {
+    for patch in workspace.patches() {
+        if graph.patched().contains(patch.name()) {
+            eprintln!("{:>12} {} with {}", "Patching", patch.name(), patch.source());
+        } else {
+            eprintln!("{:>12} patch for {} was not used", "Warning", patch.name());
+        }
+    }
+}
+
+fn native_builder(args: &ArgvMap) -> NativeBuilder {
+    let profile = if args.get_bool("--release") { Profile::Release } else { Profile::Debug };
+    let mut builder = NativeBuilder::new(profile);
 
-    // Assume the name from the current directory
-    // TODO: Move this into a utility in clink
-    use std::path::PathBuf;
-    let path = PathBuf::from("./").canonicalize().unwrap();
-    let top = path.iter().last().unwrap();
+    let jobs = args.get_str("--jobs");
+    if !jobs.is_empty() {
+        let jobs = jobs.parse().unwrap_or_else(|_| {
+            eprintln!("Error: \"{}\" is not a valid amount of jobs", jobs);
+            process::exit(1);
+        });
+        builder = builder.jobs(jobs);
+    }
+
+    builder
+}
 
-    // Create the new project and write it to a file
-    // TODO: Clean up
-    let proj = Project::new(top.to_str().unwrap().into());
-    let toml = proj.to_toml();
-    use std::fs::File;
-    use std::io::Write;
-    let mut file = File::create("./Clink.toml").unwrap();
-    write!(file, "{}", toml).unwrap();
+fn try_tree(argv: Vec<String>, config: &Config) -> Result<(), ClinkError> {
+    let args = parse_args(TREE_USAGE, argv);
+
+    let workspace = try!(config.workspace());
+    let graph = try!(resolve_read_only(&workspace, &args));
+
+    let invert = match args.get_str("--invert") {
+        "" => None,
+        name => Some(name),
+    };
+    let output = match (args.get_str("--format"), invert) {
+        ("text", None) => render_tree(&graph),
+        ("text", Some(name)) => try!(render_inverted_tree(&graph, name)),
+        ("dot", invert) => try!(render_dot(&graph, invert)),
+        (format, _) => return Err(ClinkError::InvalidProjectFile(
+            format!("\"{}\" is not a valid format, use text or dot", format)
+        )),
+    };
+    print!("{}", output);
 
     Ok(())
 }
+
+fn try_metadata(argv: Vec<String>, config: &Config) -> Result<(), ClinkError> {
+    let args = parse_args(METADATA_USAGE, argv);
+
+    let format = args.get_str("--format");
+    if format != "json" {
+        return Err(ClinkError::InvalidProjectFile(
+            format!("\"{}\" is not a valid format, use json", format)
+        ));
+    }
+
+    let workspace = try!(config.workspace());
+    let graph = try!(resolve_read_only(&workspace, &args));
+    print!("{}", render_metadata(&graph));
+
+    Ok(())
+}
+
+fn try_add(argv: Vec<String>, config: &Config) -> Result<(), ClinkError> {
+    let args = parse_args(ADD_USAGE, argv);
+    let name = args.get_str("<name>");
+
+    let project = try!(config.project());
+    let current_dir = try!(env::current_dir().map_err(|e| ClinkError::Io(PathBuf::from("."), e)));
+    let mut dep = match args.get_str("--git") {
+        "" => Dependency::at(current_dir, name.into(), args.get_str("--path")),
+        url => {
+            let reference = match (args.get_str("--branch"), args.get_str("--tag"), args.get_str("--rev")) {
+                ("", "", "") => GitReference::DefaultBranch,
+                (branch, "", "") => GitReference::Branch(branch.into()),
+                ("", tag, "") => GitReference::Tag(tag.into()),
+                (_, _, rev) => GitReference::Rev(rev.into()),
+            };
+            Dependency::git(name.into(), GitSource::new(url.into(), reference))
+        },
+    };
+    if !args.get_str("--package").is_empty() {
+        dep = dep.with_package(args.get_str("--package").into());
+    }
+    if !args.get_str("--version").is_empty() {
+        let version = args.get_str("--version");
+        dep = dep.with_version(try!(VersionReq::parse(version).map_err(|e| ClinkError::InvalidProjectFile(
+            format!("\"{}\" is not a valid version requirement: {}", version, e)
+        ))));
+    }
+    dep = dep
+        .with_features(split_list(args.get_str("--features")), !args.get_bool("--no-default-features"))
+        .with_optional(args.get_bool("--optional"))
+        .with_public(args.get_bool("--public"));
+
+    // Make sure the dependency actually is the project it says it is before adding it
+    let found = match *dep.source() {
+        DependencySource::Path(_) => try!(dep.open(project.name())),
+        DependencySource::Git(ref git) => {
+            let (_, path) = try!(git.checkout(None));
+            try!(dep.open_at(project.name(), &path))
+        },
+    };
+
+    try!(found.enabled_features(&dep.features().iter().cloned().collect()));
+
+    let table = if args.get_bool("--dev") { "dev-dependencies" } else { "dependencies" };
+    try!(add_dependency(project.path(), table, &dep));
+    println!("{:>12} {} ({}) to [{}]", "Adding", found.name(), dep.source(), table);
+
+    Ok(())
+}
+
+fn try_remove(argv: Vec<String>, config: &Config) -> Result<(), ClinkError> {
+    let args = parse_args(REMOVE_USAGE, argv);
+    let name = args.get_str("<name>");
+
+    let project = try!(config.project());
+    let table = if args.get_bool("--dev") { "dev-dependencies" } else { "dependencies" };
+    try!(remove_dependency(project.path(), table, name));
+    println!("{:>12} {} from [{}]", "Removing", name, table);
+
+    Ok(())
+}
+
+fn try_check(argv: Vec<String>, config: &Config) -> Result<(), ClinkError> {
+    parse_args(CHECK_USAGE, argv);
+
+    let report = try!(check_manifests(try!(config.start_path())));
+    for diagnostic in &report.diagnostics {
+        eprint!("{}\n\n", diagnostic);
+    }
+
+    let errors = report.diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    let warnings = report.diagnostics.len() - errors;
+    println!("{:>12} {} manifest(s), {} error(s), {} warning(s)", "Checked", report.manifests.len(), errors, warnings);
+
+    if report.has_errors() {
+        return Err(ClinkError::InvalidProjectFile(format!("Found {} error(s) in the manifests", errors)));
+    }
+
+    Ok(())
+}
+
+fn try_init(argv: Vec<String>, _config: &Config) -> Result<(), ClinkError> {
+    let args = parse_args(INIT_USAGE, argv);
+
+    let path = PathBuf::from("./");
+    let mut new = try!(new_project_args(&args, &path));
+    new.force = args.get_bool("--force");
+    try!(init_project(&path, &new));
+    println!("{:>12} {} project \"{}\"", "Created", new.class.to_string(), new.name);
+
+    Ok(())
+}
+
+fn try_new(argv: Vec<String>, _config: &Config) -> Result<(), ClinkError> {
+    let args = parse_args(NEW_USAGE, argv);
+
+    let path = PathBuf::from(args.get_str("<dir>"));
+    let new = try!(new_project_args(&args, &path));
+    try!(new_project(&path, &new));
+    println!("{:>12} {} project \"{}\" in {}", "Created", new.class.to_string(), new.name, path.display());
+
+    Ok(())
+}
+
+/// Read the options shared by init and new, taking the name from the project's directory if not
+/// given.
+fn new_project_args(args: &ArgvMap, path: &Path) -> Result<NewProject, ClinkError> {
+    let name = match args.get_str("--name") {
+        "" => {
+            let absolute = try!(env::current_dir().map_err(|e| ClinkError::Io(PathBuf::from("."), e))).join(path);
+            let absolute = try!(absolute.canonicalize().or_else(|_| Ok(absolute.clone()))
+                .map_err(|e| ClinkError::Io(absolute.clone(), e)));
+            try!(absolute.file_name().and_then(|n| n.to_str()).map(|n| n.to_string()).ok_or_else(||
+                ClinkError::InvalidProjectStructure(absolute.clone(), "Could not get a project name from the directory, use --name".into())
+            ))
+        },
+        name => name.into(),
+    };
+
+    let mut new = NewProject::new(name, try!(ProjectClass::parse(args.get_str("--type"))));
+    if !args.get_str("--template").is_empty() {
+        new.template = Some(try!(find_template(args.get_str("--template"))));
+    }
+    Ok(new)
+}
+
+/// Split a comma separated list from the command line.
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|f| f.trim())
+        .filter(|f| !f.is_empty())
+        .map(|f| f.to_string())
+        .collect()
+}
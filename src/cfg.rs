@@ -0,0 +1,218 @@
+use std::env;
+use std::fmt::{self, Display, Formatter};
+use ClinkError;
+
+/// The operating system and architecture a project is built for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Platform {
+    /// The operating system, as in Rust's `target_os`, such as "windows" or "linux".
+    pub os: String,
+    /// "windows" or "unix".
+    pub family: String,
+    /// The architecture, as in Rust's `target_arch`, such as "x86" or "x86_64".
+    pub arch: String,
+}
+
+impl Platform {
+    pub fn new(os: &str, arch: &str) -> Self {
+        Platform {
+            os: os.into(),
+            family: if os == "windows" { "windows" } else { "unix" }.into(),
+            arch: arch.into(),
+        }
+    }
+
+    /// The platform clink is running on.
+    pub fn host() -> Self {
+        Platform::new(env::consts::OS, env::consts::ARCH)
+    }
+}
+
+/// A condition on the platform, written as `cfg(...)` in the keys of [target] tables.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cfg {
+    /// A bare name, such as `windows`, `unix` or `linux`.
+    Name(String),
+    /// A key with a value, such as `target_os = "linux"`.
+    KeyValue(String, String),
+    All(Vec<Cfg>),
+    Any(Vec<Cfg>),
+    Not(Box<Cfg>),
+}
+
+impl Cfg {
+    pub fn parse(value: &str) -> Result<Self, ClinkError> {
+        let invalid = || ClinkError::InvalidProjectFile(format!("\"{}\" is not a valid cfg expression", value));
+
+        let tokens = try!(tokenize(value).ok_or_else(&invalid));
+        let mut parser = CfgParser { tokens: &tokens, position: 0 };
+
+        // The whole expression is wrapped in cfg()
+        if parser.next() != Some(&Token::Ident("cfg".into())) || parser.next() != Some(&Token::Open) {
+            return Err(invalid());
+        }
+        let cfg = try!(parser.expression().ok_or_else(&invalid));
+        if parser.next() != Some(&Token::Close) || parser.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(cfg)
+    }
+
+    pub fn matches(&self, platform: &Platform) -> bool {
+        match *self {
+            Cfg::Name(ref name) => name == &platform.family || name == &platform.os,
+            Cfg::KeyValue(ref key, ref value) => match key.as_str() {
+                "target_os" => value == &platform.os,
+                "target_family" => value == &platform.family,
+                "target_arch" => value == &platform.arch,
+                _ => false,
+            },
+            Cfg::All(ref cfgs) => cfgs.iter().all(|c| c.matches(platform)),
+            Cfg::Any(ref cfgs) => cfgs.iter().any(|c| c.matches(platform)),
+            Cfg::Not(ref cfg) => !cfg.matches(platform),
+        }
+    }
+}
+
+impl Display for Cfg {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Cfg::Name(ref name) => write!(f, "{}", name),
+            Cfg::KeyValue(ref key, ref value) => write!(f, "{} = \"{}\"", key, value),
+            Cfg::All(ref cfgs) => write!(f, "all({})", join(cfgs)),
+            Cfg::Any(ref cfgs) => write!(f, "any({})", join(cfgs)),
+            Cfg::Not(ref cfg) => write!(f, "not({})", cfg),
+        }
+    }
+}
+
+fn join(cfgs: &[Cfg]) -> String {
+    cfgs.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Open,
+    Close,
+    Comma,
+    Equals,
+}
+
+fn tokenize(value: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            '=' => tokens.push(Token::Equals),
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => string.push(c),
+                        None => return None,
+                    }
+                }
+                tokens.push(Token::Str(string));
+            },
+            c if c.is_whitespace() => {},
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_alphanumeric() && c != '_' {
+                        break;
+                    }
+                    ident.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Ident(ident));
+            },
+            _ => return None,
+        }
+    }
+
+    Some(tokens)
+}
+
+struct CfgParser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> CfgParser<'a> {
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn expression(&mut self) -> Option<Cfg> {
+        let name = match self.next() {
+            Some(Token::Ident(name)) => name.clone(),
+            _ => return None,
+        };
+
+        match self.peek() {
+            Some(&Token::Equals) => {
+                self.next();
+                match self.next() {
+                    Some(Token::Str(value)) => Some(Cfg::KeyValue(name, value.clone())),
+                    _ => None,
+                }
+            },
+            Some(&Token::Open) => {
+                self.next();
+                let mut cfgs = Vec::new();
+                while self.peek() != Some(&Token::Close) {
+                    match self.expression() {
+                        Some(cfg) => cfgs.push(cfg),
+                        None => return None,
+                    }
+                    if self.peek() == Some(&Token::Comma) {
+                        self.next();
+                    } else if self.peek() != Some(&Token::Close) {
+                        return None;
+                    }
+                }
+                self.next();
+
+                match name.as_str() {
+                    "all" => Some(Cfg::All(cfgs)),
+                    "any" => Some(Cfg::Any(cfgs)),
+                    "not" if cfgs.len() == 1 => Some(Cfg::Not(Box::new(cfgs.pop().unwrap()))),
+                    _ => None,
+                }
+            },
+            _ => Some(Cfg::Name(name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cfg, Platform};
+
+    #[test]
+    fn parse_matches_platforms() {
+        let cfg = Cfg::parse("cfg(all(windows, not(target_arch = \"x86\")))").ok().unwrap();
+        assert!(cfg.matches(&Platform::new("windows", "x86_64")));
+        assert!(!cfg.matches(&Platform::new("windows", "x86")));
+        assert!(!cfg.matches(&Platform::new("linux", "x86_64")));
+
+        let cfg = Cfg::parse("cfg(any(linux, target_os = \"macos\"))").ok().unwrap();
+        assert!(cfg.matches(&Platform::new("macos", "aarch64")));
+        assert!(Cfg::parse("cfg(unix)").ok().unwrap().matches(&Platform::new("linux", "x86")));
+        assert!(Cfg::parse("cfg(windows").is_err());
+    }
+}
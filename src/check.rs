@@ -0,0 +1,451 @@
+use std::collections::HashSet;
+use std::fmt::{self, Display, Formatter};
+use std::mem;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use dependency::{self, DEPENDENCY_KEYS, Dependency, DependencySource};
+use files;
+use project::{self, Project};
+use wincanonicalize::wincanonicalize;
+use workspace::{find_workspace_root, match_members};
+use ClinkError;
+
+const ROOT_KEYS: &[&str] = &[
+    "package", "lib", "bin", "example", "test", "dependencies", "dev-dependencies", "build", "target",
+    "features", "patch", "workspace",
+];
+const PACKAGE_KEYS: &[&str] = &["name", "version", "type"];
+const LIB_KEYS: &[&str] = &["path"];
+const TARGET_KEYS: &[&str] = &["name", "path"];
+const BUILD_KEYS: &[&str] = &["defines", "cc", "cxx"];
+const PLATFORM_KEYS: &[&str] = &["dependencies", "dev-dependencies", "build"];
+const FEATURE_KEYS: &[&str] = &["enables", "defines", "sources"];
+const WORKSPACE_KEYS: &[&str] = &["members", "exclude", "build"];
+
+/// How serious a problem found in a manifest is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    /// Something that is likely a mistake, but doesn't stop the project from being used.
+    Warning,
+    Error,
+}
+
+/// A problem found in a manifest.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The Clink.toml the problem was found in.
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}\n --> {}", severity, self.message, self.path.display())
+    }
+}
+
+/// The result of checking all manifests in a graph.
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    pub diagnostics: Vec<Diagnostic>,
+    /// The Clink.toml files that were checked.
+    pub manifests: Vec<PathBuf>,
+}
+
+impl CheckReport {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.severity == Severity::Error)
+    }
+}
+
+/// Check every manifest in the graph of the project or workspace a folder belongs to, without
+/// resolving or writing anything. All problems found are collected instead of stopping at the
+/// first one.
+pub fn check_manifests<P: AsRef<Path>>(start: P) -> Result<CheckReport, ClinkError> {
+    let root = try!(find_workspace_root(start));
+
+    let mut checker = Checker {
+        report: CheckReport::default(),
+        visited: HashSet::new(),
+        stack: Vec::new(),
+    };
+    checker.check_root(&root);
+    Ok(checker.report)
+}
+
+struct Checker {
+    report: CheckReport,
+    /// Folders of the projects that were already checked.
+    visited: HashSet<PathBuf>,
+    /// The chain of dependencies leading to the project being checked, to find cycles.
+    stack: Vec<(PathBuf, String)>,
+}
+
+impl Checker {
+    fn check_root(&mut self, path: &Path) {
+        let toml = match project::read_manifest(path) {
+            Ok(toml) => toml,
+            Err(e) => return self.error(path, e.to_string()),
+        };
+
+        let workspace = match toml.get("workspace").and_then(Value::as_table) {
+            Some(workspace) => workspace,
+            None => return self.check_project(path, None),
+        };
+
+        // A workspace checks its own manifest, and the project in it if it has one
+        if toml.contains_key("package") {
+            self.check_project(path, None);
+        } else {
+            self.check_keys(path, &toml);
+            self.report.manifests.push(files::clone_push_path(path, "Clink.toml"));
+        }
+
+        match match_members(path, workspace) {
+            Ok(members) => for member in members {
+                self.check_project(&member, None);
+            },
+            Err(e) => self.error(path, e.to_string()),
+        }
+    }
+
+    /// Check a project and everything it depends on. If it's the dependency of another project,
+    /// that dependency and the manifest it's in are given.
+    fn check_project(&mut self, path: &Path, dependent: Option<(&Dependency, &Path)>) {
+        let canonical = wincanonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+        // Depending on a project that is still being checked means we went in a circle
+        if let Some(index) = self.stack.iter().position(|(p, _)| p == &canonical) {
+            let mut cycle: Vec<String> = self.stack[index..].iter().map(|(_, n)| n.clone()).collect();
+            cycle.push(self.stack[index].1.clone());
+            let manifest = dependent.map(|(_, m)| m).unwrap_or(path);
+            return self.error(manifest, format!("Dependency cycle: {}", cycle.join(" -> ")));
+        }
+        if !self.visited.insert(canonical.clone()) {
+            return;
+        }
+
+        let toml = match project::read_manifest(path) {
+            Ok(toml) => toml,
+            Err(e) => return self.error(path, e.to_string()),
+        };
+        self.report.manifests.push(files::clone_push_path(path, "Clink.toml"));
+        self.check_keys(path, &toml);
+
+        // The dependencies are read from the manifest itself, so a broken project still has them
+        // checked
+        let dependencies = self.parse_dependencies(path, &toml, "dependencies");
+        let dev_dependencies = self.parse_dependencies(path, &toml, "dev-dependencies");
+        let patches = self.parse_dependency_table(path, toml.get("patch"));
+
+        // Opening stops at the first problem, which may have been reported above already
+        if let Err(e) = Project::open(path) {
+            let message = e.to_string();
+            let manifest = files::clone_push_path(path, "Clink.toml");
+            if !self.report.diagnostics.iter().any(|d| d.path == manifest && message.contains(&d.message)) {
+                self.error(path, message);
+            }
+        }
+
+        // The dependency has to refer to the project by its actual name
+        let name = toml.get("package").and_then(Value::as_table)
+            .and_then(|p| p.get("name")).and_then(Value::as_str)
+            .map(String::from);
+        if let (Some((dep, manifest)), Some(name)) = (dependent, name.as_ref()) {
+            if name != dep.package() {
+                self.error(manifest, format!(
+                    "Dependency \"{}\" expects a project named \"{}\", but {} is named \"{}\"",
+                    dep.name(), dep.package(), path.display(), name
+                ));
+            }
+        }
+
+        for patch in &patches {
+            self.check_dependency_path(patch, path);
+        }
+
+        self.stack.push((canonical, name.unwrap_or_else(|| path.display().to_string())));
+        for dep in &dependencies {
+            self.check_dependency(dep, path);
+        }
+
+        // Dev-dependencies may depend on the project again, so they don't count for cycles
+        let stack = mem::take(&mut self.stack);
+        for dep in &dev_dependencies {
+            self.check_dependency(dep, path);
+        }
+        self.stack = stack;
+        self.stack.pop();
+    }
+
+    /// Read a dependency table of a manifest and the same table of every [target] in it.
+    fn parse_dependencies(&mut self, path: &Path, toml: &Table, key: &str) -> Vec<Dependency> {
+        let mut dependencies = self.parse_dependency_table(path, toml.get(key));
+        let targets = toml.get("target").and_then(Value::as_table);
+        for target in targets.into_iter().flat_map(|t| t.values()) {
+            let table = target.as_table().and_then(|t| t.get(key));
+            dependencies.extend(self.parse_dependency_table(path, table));
+        }
+        dependencies
+    }
+
+    /// Read every dependency in a table on its own, reporting the broken ones instead of stopping.
+    /// Unknown keys were already reported by `check_keys` and are left out, so the rest of the
+    /// dependency still gets checked.
+    fn parse_dependency_table(&mut self, path: &Path, table: Option<&Value>) -> Vec<Dependency> {
+        let mut dependencies = Vec::new();
+        for (name, value) in table.and_then(Value::as_table).into_iter().flat_map(|t| t.iter()) {
+            let value = match *value {
+                Value::Table(ref table) => Value::Table(table.iter()
+                    .filter(|&(key, _)| DEPENDENCY_KEYS.contains(&key.as_str()))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect()),
+                ref value => value.clone(),
+            };
+            match Dependency::parse(path, name.clone(), &value) {
+                Ok(dep) => dependencies.push(dep),
+                Err(e) => self.error(path, e.to_string()),
+            }
+        }
+        dependencies
+    }
+
+    fn check_dependency(&mut self, dep: &Dependency, manifest: &Path) {
+        // Git dependencies are only checked once they're fetched by resolving the graph
+        if !self.check_dependency_path(dep, manifest) || dep.is_external() {
+            return;
+        }
+        if let DependencySource::Path(ref path) = *dep.source() {
+            self.check_project(path, Some((dep, manifest)));
+        }
+    }
+
+    /// Check that the folder of a path dependency exists.
+    fn check_dependency_path(&mut self, dep: &Dependency, manifest: &Path) -> bool {
+        match *dep.source() {
+            DependencySource::Path(ref path) if !path.exists() => {
+                self.error(manifest, format!(
+                    "Could not find dependency \"{}\", {} does not exist", dep.name(), path.display()
+                ));
+                false
+            },
+            DependencySource::Path(_) => true,
+            DependencySource::Git(_) => false,
+        }
+    }
+
+    /// Warn about every table and key in a manifest that clink doesn't know.
+    fn check_keys(&mut self, path: &Path, toml: &Table) {
+        self.unknown_keys(path, toml, ROOT_KEYS, None);
+
+        for (key, value) in toml {
+            let table = value.as_table();
+            match key.as_str() {
+                "package" => self.unknown_table_keys(path, table, PACKAGE_KEYS, "[package]"),
+                "lib" => self.unknown_table_keys(path, table, LIB_KEYS, "[lib]"),
+                "bin" | "example" | "test" => for entry in value.as_slice().unwrap_or(&[]) {
+                    self.unknown_table_keys(path, entry.as_table(), TARGET_KEYS, &format!("[[{}]]", key));
+                },
+                "dependencies" | "dev-dependencies" | "patch" => self.dependency_keys(path, table),
+                "build" => self.unknown_table_keys(path, table, BUILD_KEYS, "[build]"),
+                "target" => for (cfg, platform) in table.into_iter().flat_map(|t| t.iter()) {
+                    let context = format!("[target.'{}']", cfg);
+                    let platform = platform.as_table();
+                    self.unknown_table_keys(path, platform, PLATFORM_KEYS, &context);
+                    for deps in &["dependencies", "dev-dependencies"] {
+                        self.dependency_keys(path, platform.and_then(|t| t.get(*deps)).and_then(Value::as_table));
+                    }
+                    let build = platform.and_then(|t| t.get("build")).and_then(Value::as_table);
+                    self.unknown_table_keys(path, build, BUILD_KEYS, &format!("{}.build", context));
+                },
+                "features" => for (name, feature) in table.into_iter().flat_map(|t| t.iter()) {
+                    self.unknown_table_keys(path, feature.as_table(), FEATURE_KEYS, &format!("feature \"{}\"", name));
+                },
+                "workspace" => {
+                    self.unknown_table_keys(path, table, WORKSPACE_KEYS, "[workspace]");
+                    let build = table.and_then(|t| t.get("build")).and_then(Value::as_table);
+                    self.unknown_table_keys(path, build, BUILD_KEYS, "[workspace.build]");
+                },
+                _ => {},
+            }
+        }
+    }
+
+    /// Report every unknown key in the dependencies of a table. Unlike other unknown keys these are
+    /// errors, a misspelled key would quietly change which project is used or how.
+    fn dependency_keys(&mut self, path: &Path, table: Option<&Table>) {
+        for (name, dep) in table.into_iter().flat_map(|t| t.iter()) {
+            for key in dep.as_table().into_iter().flat_map(|t| t.keys()) {
+                if !DEPENDENCY_KEYS.contains(&key.as_str()) {
+                    self.error(path, dependency::unknown_key_message(name, key));
+                }
+            }
+        }
+    }
+
+    fn unknown_table_keys(&mut self, path: &Path, table: Option<&Table>, known: &[&str], context: &str) {
+        if let Some(table) = table {
+            self.unknown_keys(path, table, known, Some(context));
+        }
+    }
+
+    fn unknown_keys(&mut self, path: &Path, table: &Table, known: &[&str], context: Option<&str>) {
+        for (key, value) in table {
+            if known.contains(&key.as_str()) {
+                continue;
+            }
+
+            let (kind, quoted) = match (context, value) {
+                (None, &Value::Table(_)) => ("table", format!("[{}]", key)),
+                (None, Value::Array(entries)) if entries.first().and_then(Value::as_table).is_some() =>
+                    ("table", format!("[[{}]]", key)),
+                _ => ("key", format!("\"{}\"", key)),
+            };
+            let mut message = format!("Unknown {} {}", kind, quoted);
+            if let Some(context) = context {
+                message.push_str(&format!(" in {}", context));
+            }
+            if let Some(suggestion) = suggest(key, known) {
+                match kind {
+                    "table" => message.push_str(&format!(", did you mean [{}]?", suggestion)),
+                    _ => message.push_str(&format!(", did you mean \"{}\"?", suggestion)),
+                }
+            }
+            self.warning(path, message);
+        }
+    }
+
+    fn error(&mut self, path: &Path, message: String) {
+        self.push(Severity::Error, path, message);
+    }
+
+    fn warning(&mut self, path: &Path, message: String) {
+        self.push(Severity::Warning, path, message);
+    }
+
+    fn push(&mut self, severity: Severity, path: &Path, message: String) {
+        self.report.diagnostics.push(Diagnostic {
+            severity,
+            path: files::clone_push_path(path, "Clink.toml"),
+            message,
+        });
+    }
+}
+
+/// Find the known key closest to an unknown one, if any is close enough to be a typo.
+pub fn suggest<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+    known.iter()
+        .map(|k| (edit_distance(key, k), *k))
+        .filter(|&(distance, k)| distance <= 2 && distance < k.len())
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, k)| k)
+}
+
+/// The amount of single character insertions, removals and substitutions between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..b.len() + 1).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec!(i + 1);
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitute.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::Path;
+    use dependency::DEPENDENCY_KEYS;
+    use files::TempDir;
+    use super::{ROOT_KEYS, Severity, check_manifests, edit_distance, suggest};
+
+    fn write_project(root: &Path, name: &str, manifest: &str) {
+        fs::create_dir_all(root.join(name)).unwrap();
+        File::create(root.join(name).join("Clink.toml")).unwrap().write_all(manifest.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn suggest_finds_close_keys() {
+        assert_eq!(edit_distance("typ", "type"), 1);
+        assert_eq!(suggest("dependancies", ROOT_KEYS), Some("dependencies"));
+        assert_eq!(suggest("defualt-features", DEPENDENCY_KEYS), Some("default-features"));
+        assert_eq!(suggest("verison", DEPENDENCY_KEYS), Some("version"));
+        assert_eq!(suggest("description", ROOT_KEYS), None);
+    }
+
+    #[test]
+    fn check_manifests_reports_every_broken_manifest() {
+        let dir = TempDir::new("check");
+        let root = dir.path();
+        write_project(root, "App", concat!(
+            "[package]\nname = \"App\"\ntype = \"application\"\n",
+            "[dependencies]\nMath = { path = \"../Math\", verison = \"1\" }\nEngine = \"../Engine\"\n",
+            "Missing = \"../Missing\"\n",
+            "[dev-dependencies]\nCatch = { path = \"../Catch\", optinal = true }\n",
+            "[target.'cfg(windows)'.dependencies]\nWin = { path = \"../Win\", publc = true }\n",
+            "[patch]\nFmt = { path = \"../Fmt\" }\n",
+            "[build]\ndefnes = [\"APP\"]\n",
+        ));
+        // Math can't be opened, but its dependencies are still checked
+        write_project(root, "Math", concat!(
+            "[package]\nname = \"Math\"\ntype = \"libary\"\n",
+            "[dependencies]\nUtil = \"../Util\"\n",
+            "Broken = { git = \"https://example.com/broken.git\", tag = \"a\", rev = \"b\" }\n",
+        ));
+        write_project(root, "Util", "[package]\nname = \"Util\"\ntype = \"library\"\nauthor = \"me\"\n");
+        write_project(root, "Engine", concat!(
+            "[package]\nname = \"Core\"\ntype = \"library\"\n",
+            "[dependencies]\nApp = \"../App\"\n",
+        ));
+        write_project(root, "Catch", "[package]\nname = \"Catch\"\ntype = \"library\"\n");
+        write_project(root, "Win", "[package]\nname = \"Win\"\ntype = \"library\"\n");
+
+        let report = check_manifests(root.join("App")).unwrap();
+        let root = root.canonicalize().unwrap();
+        let diagnostics: Vec<(Severity, String, String)> = report.diagnostics.iter()
+            .map(|d| (
+                d.severity,
+                d.path.parent().unwrap().file_name().unwrap().to_string_lossy().into_owned(),
+                d.message.replace(&root.display().to_string(), "<root>"),
+            ))
+            .collect();
+
+        // Missing folders can't be canonicalized, so they keep the path they were written with
+        let missing = Path::new("<root>").join("App").join("..");
+        let error = |project: &str, message: &str| (Severity::Error, project.into(), message.into());
+        let warning = |project: &str, message: &str| (Severity::Warning, project.into(), message.into());
+        let not_found = |name: &str| format!(
+            "Could not find dependency \"{}\", {} does not exist", name, missing.join(name).display()
+        );
+        assert_eq!(diagnostics, vec!(
+            warning("App", "Unknown key \"defnes\" in [build], did you mean \"defines\"?"),
+            error("App", "Dependency \"Math\" has an unknown key \"verison\", did you mean \"version\"?"),
+            error("App", "Dependency \"Catch\" has an unknown key \"optinal\", did you mean \"optional\"?"),
+            error("App", "Dependency \"Win\" has an unknown key \"publc\", did you mean \"public\"?"),
+            error("App", &not_found("Fmt")),
+            error("App", &format!(
+                "Dependency \"Engine\" expects a project named \"Engine\", but {} is named \"Core\"",
+                Path::new("<root>").join("Engine").display()
+            )),
+            error("Engine", "Dependency cycle: App -> Core -> App"),
+            error("Math", "Invalid project file\n Dependency \"Broken\" can only have one of branch, tag and rev"),
+            error("Math", "Invalid project file\n \"libary\" is not a valid project type"),
+            warning("Util", "Unknown key \"author\" in [package]"),
+            error("App", &not_found("Missing")),
+        ));
+        assert_eq!(report.manifests.len(), 6);
+    }
+}
@@ -1,12 +1,49 @@
-use std::path::PathBuf;
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+use semver::{Version, VersionReq};
+use toml::{Table, Value};
+use cfg::Cfg;
+use check;
+use git::{GitReference, GitSource};
 use wincanonicalize::wincanonicalize;
+use tomlvalue::{toml_table, toml_value_str, toml_value_str_array, toml_value_bool};
 use {Project, ClinkError};
 
-#[derive(Debug)]
+/// The keys a dependency's table can have.
+pub const DEPENDENCY_KEYS: &[&str] = &[
+    "path", "git", "branch", "tag", "rev", "version", "package", "optional", "public", "features",
+    "default-features",
+];
+
+/// Where the project of a dependency can be found.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DependencySource {
+    Path(PathBuf),
+    Git(GitSource),
+}
+
+impl Display for DependencySource {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            DependencySource::Path(ref path) => write!(f, "{}", path.display()),
+            DependencySource::Git(ref git) => write!(f, "{}", git.to_source_string()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Dependency {
     name: String,
-    path: PathBuf,
+    package: String,
+    source: DependencySource,
+    version: Option<VersionReq>,
     is_external: bool,
+    optional: bool,
+    public: bool,
+    features: Vec<String>,
+    default_features: bool,
+    /// The platforms the dependency is used on, if it's not used everywhere.
+    platform: Option<Cfg>,
 }
 
 impl Dependency {
@@ -14,7 +51,8 @@ impl Dependency {
         let mut path = proj_path.into();
         path.push(depstring);
 
-        let canonical = wincanonicalize(path);
+        // A missing folder is reported once the dependency is used
+        let canonical = wincanonicalize(&path).unwrap_or(path);
 
         // Figure out if it's a native clink or external dependency
         let is_external = canonical.extension()
@@ -22,33 +60,346 @@ impl Dependency {
             .unwrap_or("".into()) == "toml";
 
         Dependency {
+            package: name.clone(),
             name: name,
-            path: canonical,
+            source: DependencySource::Path(canonical),
+            version: None,
             is_external: is_external,
+            optional: false,
+            public: false,
+            features: Vec::new(),
+            default_features: true,
+            platform: None,
         }
     }
 
+    pub fn git(name: String, source: GitSource) -> Self {
+        Dependency {
+            package: name.clone(),
+            name,
+            source: DependencySource::Git(source),
+            version: None,
+            is_external: false,
+            optional: false,
+            public: false,
+            features: Vec::new(),
+            default_features: true,
+            platform: None,
+        }
+    }
+
+    /// Read a dependency from its entry in a [dependencies] table, this is either the path to the
+    /// dependency or a table with the path or git repository and additional information.
+    pub fn parse<P: Into<PathBuf>>(proj_path: P, name: String, value: &Value) -> Result<Self, ClinkError> {
+        if let Some(depstring) = value.as_str() {
+            return Ok(Dependency::at(proj_path, name, depstring));
+        }
+
+        let table = try!(value.as_table().ok_or_else(||
+            ClinkError::InvalidProjectFile(format!("{} is invalid type (expected string or table)", name))
+        ));
+
+        // A misspelled key would otherwise silently do nothing
+        if let Some(key) = table.keys().find(|k| !DEPENDENCY_KEYS.contains(&k.as_str())) {
+            return Err(ClinkError::InvalidProjectFile(unknown_key_message(&name, key)));
+        }
+
+        let mut dep = match (table.contains_key("path"), table.contains_key("git")) {
+            (true, false) => Dependency::at(proj_path, name, try!(toml_value_str(table, "path"))),
+            (false, true) => {
+                let source = try!(parse_git_source(table, &name));
+                Dependency::git(name, source)
+            },
+            _ => return Err(ClinkError::InvalidProjectFile(
+                format!("Dependency \"{}\" needs either a path or a git repository", name)
+            )),
+        };
+        if table.contains_key("package") {
+            dep.package = try!(toml_value_str(table, "package")).into();
+        }
+        if table.contains_key("version") {
+            let version = try!(toml_value_str(table, "version"));
+            dep.version = Some(try!(VersionReq::parse(version).map_err(|e| ClinkError::InvalidProjectFile(
+                format!("Dependency \"{}\" has an invalid version requirement \"{}\": {}", dep.name, version, e)
+            ))));
+        }
+        if table.contains_key("optional") {
+            dep.optional = try!(toml_value_bool(table, "optional"));
+        }
+        if table.contains_key("public") {
+            dep.public = try!(toml_value_bool(table, "public"));
+        }
+        if table.contains_key("features") {
+            dep.features = try!(toml_value_str_array(table, "features"));
+        }
+        if table.contains_key("default-features") {
+            dep.default_features = try!(toml_value_bool(table, "default-features"));
+        }
+
+        Ok(dep)
+    }
+
+    /// The name the dependency is known by in the project depending on it.
     pub fn name(&self) -> &String {
         &self.name
     }
 
-    pub fn path(&self) -> &PathBuf {
-        &self.path
+    /// The name of the project the dependency refers to, this is the same as the dependency's
+    /// name unless it was renamed.
+    pub fn package(&self) -> &String {
+        &self.package
+    }
+
+    pub fn source(&self) -> &DependencySource {
+        &self.source
+    }
+
+    /// The folder of the dependency's project, if it's a path dependency.
+    pub fn path(&self) -> Option<&PathBuf> {
+        match self.source {
+            DependencySource::Path(ref path) => Some(path),
+            DependencySource::Git(_) => None,
+        }
+    }
+
+    /// The versions of the dependency's project this dependency accepts, if restricted.
+    pub fn version(&self) -> Option<&VersionReq> {
+        self.version.as_ref()
     }
 
     pub fn is_external(&self) -> bool {
         self.is_external
     }
 
-    pub fn open(&self) -> Result<Project, ClinkError> {
+    /// Check if this dependency is only used when a feature enables it.
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+
+    /// Check if the dependency's include folder should also be available to projects depending
+    /// on the project that has this dependency.
+    pub fn is_public(&self) -> bool {
+        self.public
+    }
+
+    /// The features requested from the dependency.
+    pub fn features(&self) -> &Vec<String> {
+        &self.features
+    }
+
+    /// Check if the dependency's default features should be enabled.
+    pub fn default_features(&self) -> bool {
+        self.default_features
+    }
+
+    /// Only use the dependency on platforms matching a condition.
+    pub fn with_platform(mut self, platform: Cfg) -> Self {
+        self.platform = Some(platform);
+        self
+    }
+
+    /// Refer to a project with a different name than the dependency's.
+    pub fn with_package(mut self, package: String) -> Self {
+        self.package = package;
+        self
+    }
+
+    /// Only accept versions of the dependency's project matching a requirement.
+    pub fn with_version(mut self, version: VersionReq) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Only use the dependency when a feature enables it.
+    pub fn with_optional(mut self, optional: bool) -> Self {
+        self.optional = optional;
+        self
+    }
+
+    /// Pass the dependency's include folder on to projects depending on this project.
+    pub fn with_public(mut self, public: bool) -> Self {
+        self.public = public;
+        self
+    }
+
+    /// Request features from the dependency.
+    pub fn with_features(mut self, features: Vec<String>, default_features: bool) -> Self {
+        self.features = features;
+        self.default_features = default_features;
+        self
+    }
+
+    /// The condition on the platforms the dependency is used on, if any.
+    pub fn platform(&self) -> Option<&Cfg> {
+        self.platform.as_ref()
+    }
+
+    /// Open the project of a path dependency, for the project named `dependent` depending on it.
+    pub fn open(&self, dependent: &str) -> Result<Project, ClinkError> {
+        match self.source {
+            DependencySource::Path(ref path) => self.open_at(dependent, path),
+            DependencySource::Git(ref git) => Err(ClinkError::InvalidProjectFile(format!(
+                "Dependency \"{}\" on {} needs to be checked out before it can be opened", self.name, git.url
+            ))),
+        }
+    }
+
+    /// Open the project of this dependency from the folder it was found in, for the project named
+    /// `dependent` depending on it.
+    pub fn open_at(&self, dependent: &str, path: &Path) -> Result<Project, ClinkError> {
         // Can't open external projects as clink projects
         if self.is_external {
-            Err(ClinkError::InvalidProjectStructure(
-                self.path.clone(),
+            return Err(ClinkError::InvalidProjectStructure(
+                path.to_path_buf(),
                 "Cannot open external dependency as project".into()
-            ))
-        } else {
-            Project::open(&self.path)
+            ));
+        }
+
+        let project = try!(Project::open(path));
+
+        // Make sure we actually found the project we were looking for
+        if project.name() != &self.package {
+            return Err(ClinkError::InvalidProjectStructure(path.to_path_buf(), format!(
+                "Dependency \"{}\" expects a project named \"{}\", but found \"{}\"",
+                self.name, self.package, project.name()
+            )));
+        }
+
+        // And that it's a version we accept
+        if !self.accepts(project.version()) {
+            return Err(ClinkError::InvalidProjectStructure(path.to_path_buf(), format!(
+                "\"{}\" requires version {} of \"{}\", but found \"{}\" {}",
+                dependent, self.version.as_ref().unwrap(), self.package, project.name(), version_string(project.version())
+            )));
+        }
+
+        Ok(project)
+    }
+
+    /// Check if a version of the dependency's project matches this dependency's requirement.
+    pub fn accepts(&self, version: Option<&Version>) -> bool {
+        match (self.version.as_ref(), version) {
+            (None, _) => true,
+            (Some(req), Some(version)) => req.matches(version),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Describe a project's version for error messages.
+pub fn version_string(version: Option<&Version>) -> String {
+    version.map(|v| v.to_string()).unwrap_or_else(|| "no version".into())
+}
+
+/// Describe a key in a dependency that clink doesn't know, with the key it was likely meant to be.
+pub fn unknown_key_message(name: &str, key: &str) -> String {
+    let hint = check::suggest(key, DEPENDENCY_KEYS)
+        .map(|k| format!(", did you mean \"{}\"?", k))
+        .unwrap_or_default();
+    format!("Dependency \"{}\" has an unknown key \"{}\"{}", name, key, hint)
+}
+
+/// Read the [patch] table of a root manifest. Every dependency on a package in it, anywhere in the
+/// graph, comes from the patch's path or git repository instead.
+pub fn parse_patches(toml: &Table, path: &Path) -> Result<Vec<Dependency>, ClinkError> {
+    let mut patches = Vec::new();
+    if let Some(patch_table) = toml.get("patch") {
+        let patch_table = try!(toml_table(patch_table, "patch"));
+
+        for (key, value) in patch_table {
+            patches.push(try!(Dependency::parse(path, key.clone(), value)));
         }
     }
+
+    Ok(patches)
+}
+
+fn parse_git_source(table: &Table, name: &str) -> Result<GitSource, ClinkError> {
+    let url = try!(toml_value_str(table, "git")).to_string();
+
+    let mut references = Vec::new();
+    if table.contains_key("branch") {
+        references.push(GitReference::Branch(try!(toml_value_str(table, "branch")).into()));
+    }
+    if table.contains_key("tag") {
+        references.push(GitReference::Tag(try!(toml_value_str(table, "tag")).into()));
+    }
+    if table.contains_key("rev") {
+        references.push(GitReference::Rev(try!(toml_value_str(table, "rev")).into()));
+    }
+
+    if references.len() > 1 {
+        return Err(ClinkError::InvalidProjectFile(
+            format!("Dependency \"{}\" can only have one of branch, tag and rev", name)
+        ));
+    }
+
+    Ok(GitSource::new(url, references.pop().unwrap_or(GitReference::DefaultBranch)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::Path;
+    use files::TempDir;
+    use tomlvalue::parse_toml;
+    use super::Dependency;
+
+    /// Parse the only dependency in a [dependencies] table.
+    fn parse(manifest: &str) -> Result<Dependency, String> {
+        let toml = parse_toml(Path::new("Clink.toml"), manifest).ok().unwrap();
+        let deps = toml["dependencies"].as_table().unwrap();
+        let (name, value) = deps.iter().next().unwrap();
+        Dependency::parse("/nonexistent/Game", name.clone(), value).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn parse_reads_inline_tables() {
+        let dep = parse(concat!(
+            "[dependencies]\nMath = { path = \"../Math\", package = \"RealMath\", version = \"^1.2\", ",
+            "optional = true, public = true, features = [\"simd\"], default-features = false }\n",
+        )).unwrap();
+        assert_eq!(dep.name(), "Math");
+        assert_eq!(dep.package(), "RealMath");
+        assert_eq!(dep.path().unwrap(), Path::new("/nonexistent/Game/../Math"));
+        assert_eq!(dep.version().unwrap().to_string(), "^1.2");
+        assert!(dep.is_optional() && dep.is_public() && !dep.default_features());
+        assert_eq!(dep.features(), &vec!("simd".to_string()));
+
+        let dep = parse("[dependencies]\nMath = \"../Math\"\n").unwrap();
+        assert_eq!(dep.package(), "Math");
+        assert!(!dep.is_optional() && !dep.is_public() && dep.default_features());
+    }
+
+    #[test]
+    fn parse_rejects_invalid_inline_tables() {
+        let error = parse("[dependencies]\nMath = { path = \"../Math\", optinal = true }\n").unwrap_err();
+        assert!(error.contains("unknown key \"optinal\", did you mean \"optional\"?"), "{}", error);
+
+        let error = parse("[dependencies]\nMath = { path = \"../Math\", git = \"file:///Math\" }\n").unwrap_err();
+        assert!(error.contains("needs either a path or a git repository"), "{}", error);
+        let error = parse("[dependencies]\nMath = { version = \"1.0\" }\n").unwrap_err();
+        assert!(error.contains("needs either a path or a git repository"), "{}", error);
+
+        let error = parse("[dependencies]\nMath = { path = \"../Math\", optional = \"yes\" }\n").unwrap_err();
+        assert!(error.contains("optional is invalid type (expected boolean)"), "{}", error);
+        let error = parse("[dependencies]\nMath = { path = \"../Math\", features = \"simd\" }\n").unwrap_err();
+        assert!(error.contains("features is invalid type (expected array)"), "{}", error);
+        let error = parse("[dependencies]\nMath = 5\n").unwrap_err();
+        assert!(error.contains("Math is invalid type (expected string or table)"), "{}", error);
+    }
+
+    #[test]
+    fn open_at_names_both_sides_of_version_mismatch() {
+        let dir = TempDir::new("dependency");
+        let path = dir.path();
+        File::create(path.join("Clink.toml")).unwrap()
+            .write_all(b"[package]\nname = \"Math\"\nversion = \"2.0.0\"\ntype = \"library\"\n").unwrap();
+
+        let dep = parse("[dependencies]\nMath = { path = \"../Math\", version = \"^1.0\" }\n").unwrap();
+        let error = dep.open_at("Game", path).err().unwrap().to_string();
+
+        assert!(error.contains("\"Game\" requires version ^1.0 of \"Math\", but found \"Math\" 2.0.0"), "{}", error);
+    }
 }
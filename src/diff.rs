@@ -0,0 +1,130 @@
+use std::cmp;
+
+/// Lines of unchanged text shown around each change.
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Edit {
+    Keep,
+    Remove,
+    Add,
+}
+
+/// Render a unified diff between two texts, like `diff -u` does. Returns an empty string if they
+/// are the same.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = diff_lines(&old_lines, &new_lines);
+    if edits.iter().all(|&e| e == Edit::Keep) {
+        return String::new();
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for (start, end) in hunks(&edits) {
+        // Find where in both texts the hunk starts
+        let old_start = edits[..start].iter().filter(|&&e| e != Edit::Add).count();
+        let new_start = edits[..start].iter().filter(|&&e| e != Edit::Remove).count();
+        let old_count = edits[start..end].iter().filter(|&&e| e != Edit::Add).count();
+        let new_count = edits[start..end].iter().filter(|&&e| e != Edit::Remove).count();
+
+        output.push_str(&format!("@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count), hunk_range(new_start, new_count)
+        ));
+
+        let (mut o, mut n) = (old_start, new_start);
+        for &edit in &edits[start..end] {
+            match edit {
+                Edit::Keep => { output.push_str(&format!(" {}\n", old_lines[o])); o += 1; n += 1; },
+                Edit::Remove => { output.push_str(&format!("-{}\n", old_lines[o])); o += 1; },
+                Edit::Add => { output.push_str(&format!("+{}\n", new_lines[n])); n += 1; },
+            }
+        }
+    }
+
+    output
+}
+
+/// Find the edits that turn one list of lines into another, using the longest common subsequence.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    // Lines the texts start and end with don't need to go through the table, this keeps it small
+    // for the usual case of a few changed lines
+    let prefix = old.iter().zip(new).take_while(|&(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|&(a, b)| a == b).count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    // lengths[i][j] is the length of the common subsequence of old_middle[i..] and new_middle[j..]
+    let width = new_middle.len() + 1;
+    let mut lengths = vec![0u32; (old_middle.len() + 1) * width];
+    for i in (0..old_middle.len()).rev() {
+        for j in (0..new_middle.len()).rev() {
+            lengths[i * width + j] = if old_middle[i] == new_middle[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                cmp::max(lengths[(i + 1) * width + j], lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut edits = vec![Edit::Keep; prefix];
+    let (mut i, mut j) = (0, 0);
+    while i < old_middle.len() && j < new_middle.len() {
+        if old_middle[i] == new_middle[j] {
+            edits.push(Edit::Keep);
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            edits.push(Edit::Remove);
+            i += 1;
+        } else {
+            edits.push(Edit::Add);
+            j += 1;
+        }
+    }
+    edits.extend((i..old_middle.len()).map(|_| Edit::Remove));
+    edits.extend((j..new_middle.len()).map(|_| Edit::Add));
+    edits.extend((0..suffix).map(|_| Edit::Keep));
+
+    edits
+}
+
+/// Group the edits into hunks of changes with their context, changes close together share one.
+fn hunks(edits: &[Edit]) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (index, _) in edits.iter().enumerate().filter(|&(_, &e)| e != Edit::Keep) {
+        let start = index.saturating_sub(CONTEXT);
+        let end = cmp::min(index + 1 + CONTEXT, edits.len());
+
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    hunks
+}
+
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        // An empty range points at the line before it
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unified_diff;
+
+    #[test]
+    fn unified_diff_shows_changes_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let new = "a\nb\nc\nd\nE\nf\ng\nh\ni\n";
+        assert_eq!(unified_diff(old, new, "a/x", "b/x"),
+            "--- a/x\n+++ b/x\n@@ -2,7 +2,8 @@\n b\n c\n d\n-e\n+E\n f\n g\n h\n+i\n");
+
+        assert_eq!(unified_diff("", "a\n", "/dev/null", "b/x"), "--- /dev/null\n+++ b/x\n@@ -0,0 +1 @@\n+a\n");
+        assert_eq!(unified_diff(old, old, "a/x", "b/x"), "");
+    }
+}
@@ -0,0 +1,212 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use toml::Value;
+use dependency::{Dependency, DependencySource};
+use files;
+use git::GitReference;
+use project;
+use tomlvalue::parse_toml;
+use wincanonicalize::wincanonicalize;
+use ClinkError;
+
+/// Add a dependency to a table of a project's Clink.toml, or replace it if it's already there.
+/// Only the dependency's line is changed, comments and formatting of the rest of the file stay
+/// as they are. A dependency with only a path is written in the short form.
+pub fn add_dependency(project_path: &Path, table: &str, dependency: &Dependency) -> Result<(), ClinkError> {
+    let text = try!(project::read_manifest_text(project_path));
+    let fields = try!(dependency_fields(project_path, dependency));
+    let entry = format!("{} = {}", key(dependency.name()), dependency_value(&fields));
+    let edited = try!(insert_entry(&text, table, dependency.name(), &entry));
+    write_manifest(project_path, &edited)
+}
+
+/// Remove a dependency from a table of a project's Clink.toml, keeping the rest of the file as
+/// it is.
+pub fn remove_dependency(project_path: &Path, table: &str, name: &str) -> Result<(), ClinkError> {
+    let text = try!(project::read_manifest_text(project_path));
+    let edited = try!(remove_entry(&text, table, name).ok_or_else(|| ClinkError::InvalidProjectFile(
+        format!("\"{}\" is not in [{}]", name, table)
+    )));
+    write_manifest(project_path, &edited)
+}
+
+/// Write an edited manifest, if it's still valid toml.
+fn write_manifest(project_path: &Path, text: &str) -> Result<(), ClinkError> {
+    let path = files::clone_push_path(project_path, "Clink.toml");
+    try!(parse_toml(&path, text));
+
+    File::create(&path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|e| ClinkError::WriteFailed(path, e))
+}
+
+/// The fields of a dependency's entry, the way they would be written by hand.
+fn dependency_fields(project_path: &Path, dep: &Dependency) -> Result<Vec<(&'static str, Value)>, ClinkError> {
+    let mut fields = Vec::new();
+    match *dep.source() {
+        DependencySource::Path(ref path) => {
+            let relative = files::relative_path(&try!(wincanonicalize(project_path)), path);
+            fields.push(("path", Value::String(relative.to_string_lossy().replace('\\', "/"))));
+        },
+        DependencySource::Git(ref git) => {
+            fields.push(("git", Value::String(git.url.clone())));
+            match git.reference {
+                GitReference::Branch(ref branch) => fields.push(("branch", Value::String(branch.clone()))),
+                GitReference::Tag(ref tag) => fields.push(("tag", Value::String(tag.clone()))),
+                GitReference::Rev(ref rev) => fields.push(("rev", Value::String(rev.clone()))),
+                GitReference::DefaultBranch => {},
+            }
+        },
+    }
+
+    if dep.package() != dep.name() {
+        fields.push(("package", Value::String(dep.package().clone())));
+    }
+    if let Some(version) = dep.version() {
+        fields.push(("version", Value::String(version.to_string())));
+    }
+    if dep.is_optional() {
+        fields.push(("optional", Value::Boolean(true)));
+    }
+    if dep.is_public() {
+        fields.push(("public", Value::Boolean(true)));
+    }
+    if !dep.features().is_empty() {
+        fields.push(("features", Value::Array(dep.features().iter().map(|f| Value::String(f.clone())).collect())));
+    }
+    if !dep.default_features() {
+        fields.push(("default-features", Value::Boolean(false)));
+    }
+
+    Ok(fields)
+}
+
+fn dependency_value(fields: &[(&str, Value)]) -> String {
+    match fields {
+        [(field, ref value)] if *field == "path" => value.to_string(),
+        _ => {
+            let fields: Vec<String> = fields.iter().map(|&(k, ref v)| format!("{} = {}", k, v)).collect();
+            format!("{{ {} }}", fields.join(", "))
+        },
+    }
+}
+
+/// Insert or replace an entry in a table, adding the table at the end if it doesn't exist.
+fn insert_entry(text: &str, table: &str, name: &str, entry: &str) -> Result<String, ClinkError> {
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines: Vec<String> = text.lines().map(|l| l.to_string()).collect();
+
+    if find_table(&lines, &format!("{}.{}", table, name)).is_some() {
+        return Err(ClinkError::InvalidProjectFile(format!(
+            "\"{}\" has its own [{}.{}] table, edit it by hand instead", name, table, name
+        )));
+    }
+
+    match find_table(&lines, table) {
+        Some((start, end)) => {
+            match find_key(&lines, start, end, name) {
+                Some(index) => lines[index] = entry.into(),
+                None => {
+                    // Put it after the last entry, comments and blank lines after that belong to what follows
+                    let last = (start + 1..end).rev().find(|&i| line_key(&lines[i]).is_some()).unwrap_or(start);
+                    lines.insert(last + 1, entry.into());
+                },
+            }
+        },
+        None => {
+            if lines.last().map(|l| !l.trim().is_empty()).unwrap_or(false) {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{}]", table));
+            lines.push(entry.into());
+        },
+    }
+
+    Ok(lines.join(newline) + newline)
+}
+
+/// Remove an entry from a table, or its own sub-table. Returns None if it isn't there.
+fn remove_entry(text: &str, table: &str, name: &str) -> Option<String> {
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines: Vec<String> = text.lines().map(|l| l.to_string()).collect();
+
+    if let Some((start, end)) = find_table(&lines, &format!("{}.{}", table, name)) {
+        lines.drain(start..end);
+    } else {
+        match find_table(&lines, table).and_then(|(start, end)| find_key(&lines, start, end, name)) {
+            Some(index) => lines.remove(index),
+            None => return None,
+        };
+    }
+
+    Some(lines.join(newline) + newline)
+}
+
+/// Find the line of a table's header, and the line after its last line.
+fn find_table(lines: &[String], table: &str) -> Option<(usize, usize)> {
+    lines.iter().position(|l| table_header(l).as_deref() == Some(table)).map(|start| {
+        let end = (start + 1..lines.len())
+            .find(|&i| lines[i].trim_start().starts_with('['))
+            .unwrap_or(lines.len());
+        (start, end)
+    })
+}
+
+/// Find the line of a key in a table.
+fn find_key(lines: &[String], start: usize, end: usize, key: &str) -> Option<usize> {
+    (start + 1..end).find(|&i| line_key(&lines[i]).as_deref() == Some(key))
+}
+
+/// The name of the table a line starts, if it's a table header.
+fn table_header(line: &str) -> Option<String> {
+    let line = line.trim();
+    if !line.starts_with('[') || line.starts_with("[[") {
+        return None;
+    }
+    line[1..].find(']').map(|end| line[1..end + 1].trim().to_string())
+}
+
+/// The key of a line with a key and value.
+fn line_key(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') || line.starts_with('[') {
+        return None;
+    }
+    line.find('=').map(|end| line[..end].trim().trim_matches('"').to_string())
+}
+
+/// Write a key, quoting it if needed.
+fn key(name: &str) -> String {
+    if name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+        name.into()
+    } else {
+        Value::String(name.into()).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{insert_entry, remove_entry};
+
+    const MANIFEST: &str = "[package]\nname = \"Game\" # The game\n\n\
+        [dependencies]\n# Rendering\nEngine = \"../Engine\"\n\n# Tools\n[build]\ndefines = []\n";
+
+    #[test]
+    fn insert_entry_keeps_comments() {
+        let edited = insert_entry(MANIFEST, "dependencies", "Math", "Math = \"../Math\"").ok().unwrap();
+        assert_eq!(edited, "[package]\nname = \"Game\" # The game\n\n\
+            [dependencies]\n# Rendering\nEngine = \"../Engine\"\nMath = \"../Math\"\n\n# Tools\n[build]\ndefines = []\n");
+
+        let edited = insert_entry(MANIFEST, "dev-dependencies", "Catch", "Catch = \"../Catch\"").ok().unwrap();
+        assert!(edited.ends_with("defines = []\n\n[dev-dependencies]\nCatch = \"../Catch\"\n"));
+    }
+
+    #[test]
+    fn remove_entry_only_removes_its_line() {
+        let edited = remove_entry(MANIFEST, "dependencies", "Engine").unwrap();
+        assert_eq!(edited, "[package]\nname = \"Game\" # The game\n\n\
+            [dependencies]\n# Rendering\n\n# Tools\n[build]\ndefines = []\n");
+        assert!(remove_entry(MANIFEST, "dependencies", "Math").is_none());
+    }
+}
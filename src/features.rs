@@ -0,0 +1,163 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use dependency::Dependency;
+use files;
+use tomlvalue::{toml_table, toml_value_str_array};
+use ClinkError;
+
+/// A named feature of a project, enabled by the projects depending on it or from the command line.
+#[derive(Clone, Debug)]
+pub struct Feature {
+    pub name: String,
+    /// Other features, optional dependencies and features of dependencies ("dependency/feature")
+    /// this feature enables.
+    pub enables: Vec<String>,
+    pub defines: Vec<String>,
+    /// Extra folders with sources that are only part of the project with this feature.
+    pub sources: Vec<PathBuf>,
+}
+
+/// Which features to enable on the projects a graph is resolved from.
+#[derive(Clone, Debug, Default)]
+pub struct FeatureSelection {
+    pub features: Vec<String>,
+    pub all_features: bool,
+}
+
+impl FeatureSelection {
+    pub fn new() -> Self {
+        FeatureSelection::default()
+    }
+}
+
+/// Read the [features] table of a project file. A feature is either a list of what it enables,
+/// or a table with what it enables, its defines and its source folders.
+pub fn parse_features(
+    toml: &Table, project_path: &Path, dependencies: &[Dependency]
+) -> Result<Vec<Feature>, ClinkError> {
+    let table = match toml.get("features") {
+        Some(table) => try!(toml_table(table, "features")),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut features = Vec::new();
+    for (name, value) in table {
+        let feature = match *value {
+            Value::Array(_) => Feature {
+                name: name.clone(),
+                enables: try!(toml_value_str_array(table, name)),
+                defines: Vec::new(),
+                sources: Vec::new(),
+            },
+            Value::Table(ref entry) => {
+                let mut feature = Feature {
+                    name: name.clone(),
+                    enables: Vec::new(),
+                    defines: Vec::new(),
+                    sources: Vec::new(),
+                };
+                if entry.contains_key("enables") {
+                    feature.enables = try!(toml_value_str_array(entry, "enables"));
+                }
+                if entry.contains_key("defines") {
+                    feature.defines = try!(toml_value_str_array(entry, "defines"));
+                }
+                if entry.contains_key("sources") {
+                    for source in try!(toml_value_str_array(entry, "sources")) {
+                        let path = files::clone_push_path(project_path, &source);
+                        if !path.is_dir() {
+                            return Err(ClinkError::InvalidProjectStructure(
+                                path, format!("Could not find the sources of feature \"{}\"", name)
+                            ));
+                        }
+                        feature.sources.push(path);
+                    }
+                }
+                feature
+            },
+            _ => return Err(ClinkError::InvalidProjectFile(
+                format!("Feature \"{}\" is invalid type (expected array or table)", name)
+            )),
+        };
+        features.push(feature);
+    }
+
+    // Everything a feature enables has to exist
+    for feature in &features {
+        for entry in &feature.enables {
+            let valid = match entry.find('/') {
+                Some(index) => dependencies.iter().any(|d| d.name() == &entry[..index]),
+                None => features.iter().any(|f| &f.name == entry) ||
+                    dependencies.iter().any(|d| d.name() == entry && d.is_optional()),
+            };
+            if !valid {
+                return Err(ClinkError::InvalidProjectFile(format!(
+                    "Feature \"{}\" enables \"{}\", which is not a feature or optional dependency",
+                    feature.name, entry
+                )));
+            }
+        }
+    }
+
+    Ok(features)
+}
+
+/// Find all features enabled by a set of requested features, including the ones they enable in
+/// turn. The "default" feature may be requested even if the project doesn't have one.
+pub fn expand_features(
+    project_name: &str, features: &[Feature], requested: &BTreeSet<String>
+) -> Result<BTreeSet<String>, ClinkError> {
+    let mut enabled = BTreeSet::new();
+    let mut pending: Vec<String> = requested.iter().cloned().collect();
+
+    while let Some(name) = pending.pop() {
+        if enabled.contains(&name) {
+            continue;
+        }
+
+        match features.iter().find(|f| f.name == name) {
+            Some(feature) => {
+                pending.extend(feature.enables.iter()
+                    .filter(|e| features.iter().any(|f| &f.name == *e))
+                    .cloned()
+                );
+            },
+            None if name == "default" => continue,
+            None => return Err(ClinkError::InvalidProjectFile(
+                format!("Project \"{}\" does not have a feature named \"{}\"", project_name, name)
+            )),
+        }
+        enabled.insert(name);
+    }
+
+    Ok(enabled)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use super::{Feature, expand_features};
+
+    fn feature(name: &str, enables: &[&str]) -> Feature {
+        Feature {
+            name: name.into(),
+            enables: enables.iter().map(|e| e.to_string()).collect(),
+            defines: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn expand_features_follows_enabled_features() {
+        let features = vec!(
+            feature("default", &["logging"]),
+            feature("logging", &["Fmt", "Fmt/color"]),
+            feature("vulkan", &[]),
+        );
+        let requested: BTreeSet<String> = vec!("default".to_string()).into_iter().collect();
+
+        let enabled = expand_features("Engine", &features, &requested).ok().unwrap();
+        assert_eq!(enabled.into_iter().collect::<Vec<_>>(), vec!("default", "logging"));
+    }
+}
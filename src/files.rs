@@ -1,7 +1,143 @@
-use std::path::PathBuf;
+use sha1::Sha1;
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+use wincanonicalize::wincanonicalize;
 
-pub fn clone_push_path(path: &PathBuf, appended: &str) -> PathBuf {
-    let mut path = path.clone();
+pub fn clone_push_path(path: &Path, appended: &str) -> PathBuf {
+    let mut path = path.to_path_buf();
     path.push(appended);
     path
 }
+
+/// Hash data into a hexadecimal string that stays the same between runs and clink versions.
+pub fn hash_hex(data: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(data);
+    hasher.digest().to_string()
+}
+
+/// Derive a UUID from a name, so generated files get the same one every time they're generated.
+pub fn stable_uuid(name: &str) -> Uuid {
+    let mut hasher = Sha1::new();
+    hasher.update(name.as_bytes());
+    let mut bytes = hasher.digest().bytes();
+
+    // Mark it as a name-based SHA-1 UUID, like version 5 UUIDs are
+    bytes[6] = (bytes[6] & 0x0f) | 0x50;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    Uuid::from_bytes(&bytes[..16]).unwrap()
+}
+
+/// Get a path relative to a base folder, both need to be canonical.
+pub fn relative_path(base: &Path, path: &Path) -> PathBuf {
+    let base: Vec<Component> = base.components().collect();
+    let path: Vec<Component> = path.components().collect();
+
+    let common = base.iter().zip(path.iter()).take_while(|&(a, b)| a == b).count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &path[common..] {
+        relative.push(component.as_os_str());
+    }
+
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    relative
+}
+
+/// Get a path relative to a base folder, resolving `..` and links in both first. Parts that don't
+/// exist yet are kept as they are, and a path on another drive stays absolute.
+pub fn resolved_relative_path(base: &Path, path: &Path) -> PathBuf {
+    let base = resolve_path(base);
+    let path = resolve_path(path);
+    if base.components().next() != path.components().next() {
+        return path;
+    }
+    relative_path(&base, &path)
+}
+
+/// Canonicalize the part of a path that exists.
+fn resolve_path(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(mut resolved) = wincanonicalize(existing) {
+            for name in missing.iter().rev() {
+                resolved.push(name);
+            }
+            return resolved;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            },
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+/// A folder in the system's temporary folder for tests to write projects to. It's removed again
+/// when dropped, so a failing test doesn't leave it behind.
+#[cfg(test)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+#[cfg(test)]
+impl TempDir {
+    /// Create an empty folder, the name keeps tests running at the same time apart.
+    pub fn new(name: &str) -> TempDir {
+        let path = ::std::env::temp_dir().join(format!("clink-{}-test-{}", name, ::std::process::id()));
+        let _ = ::std::fs::remove_dir_all(&path);
+        ::std::fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = ::std::fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::fs;
+    use super::{TempDir, relative_path, resolved_relative_path, stable_uuid};
+
+    #[test]
+    fn relative_path_walks_up_to_common_folder() {
+        assert_eq!(relative_path(Path::new("/a/b/c"), Path::new("/a/d")), PathBuf::from("../../d"));
+        assert_eq!(relative_path(Path::new("/a/b"), Path::new("/a/b/c")), PathBuf::from("c"));
+        assert_eq!(relative_path(Path::new("/a/b"), Path::new("/a/b")), PathBuf::from("."));
+    }
+
+    #[test]
+    fn resolved_relative_path_resolves_existing_parts() {
+        let dir = TempDir::new("files");
+        let root = dir.path();
+        fs::create_dir_all(root.join("a").join("b")).unwrap();
+
+        let relative = resolved_relative_path(&root.join("a").join("b").join(".."), &root.join("c").join("d"));
+
+        assert_eq!(relative, Path::new("..").join("c").join("d"));
+    }
+
+    #[test]
+    fn stable_uuid_depends_only_on_name() {
+        assert_eq!(stable_uuid("project:Game"), stable_uuid("project:Game"));
+        assert!(stable_uuid("project:Game") != stable_uuid("project:Engine"));
+        assert_eq!(stable_uuid("project:Game").hyphenated().to_string().as_bytes()[14], b'5');
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read, Write};
+use std::path::PathBuf;
+use graph::ProjectGraph;
+use ClinkError;
+
+/// The files a generator produces, by the path they're written to.
+pub type GeneratedFiles = BTreeMap<PathBuf, String>;
+
+/// A backend that turns a resolved project graph into files for a build system or IDE.
+///
+/// Clink comes with a Visual Studio generator, but anything that implements this trait can be
+/// used to generate output from a graph.
+pub trait Generator {
+    /// Generate all files for a graph in memory, without writing anything to disk.
+    fn generate_files(&self, graph: &ProjectGraph) -> Result<GeneratedFiles, ClinkError>;
+
+    /// Generate all files for a graph and write them to disk.
+    fn generate(&self, graph: &ProjectGraph) -> Result<(), ClinkError> {
+        let files = try!(self.generate_files(graph));
+        write_files(&files)
+    }
+}
+
+/// How a generated file compares to the file on disk.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileStatus {
+    Created,
+    Changed,
+    Unchanged,
+}
+
+/// A generated file next to what's currently on disk.
+pub struct FileComparison<'a> {
+    pub path: &'a PathBuf,
+    pub status: FileStatus,
+    /// The file currently on disk, if there is one.
+    pub current: Option<String>,
+    pub generated: &'a String,
+}
+
+/// Compare generated files to the files on disk, without writing anything.
+pub fn compare_files(files: &GeneratedFiles) -> Result<Vec<FileComparison<'_>>, ClinkError> {
+    let mut comparisons = Vec::new();
+    for (path, generated) in files {
+        let mut bytes = Vec::new();
+        let current = match File::open(path).and_then(|mut f| f.read_to_end(&mut bytes)) {
+            Ok(_) => Some(String::from_utf8_lossy(&bytes).into_owned()),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(ClinkError::Io(path.clone(), e)),
+        };
+
+        let status = match current {
+            None => FileStatus::Created,
+            Some(ref current) if current == generated => FileStatus::Unchanged,
+            Some(_) => FileStatus::Changed,
+        };
+        comparisons.push(FileComparison {
+            path,
+            status,
+            current,
+            generated,
+        });
+    }
+
+    Ok(comparisons)
+}
+
+/// Write generated files to disk, replacing files that already exist.
+pub fn write_files(files: &GeneratedFiles) -> Result<(), ClinkError> {
+    for (path, contents) in files {
+        // Some generators put files in folders of their own
+        if let Some(parent) = path.parent() {
+            try!(fs::create_dir_all(parent).map_err(|e| ClinkError::WriteFailed(parent.to_path_buf(), e)));
+        }
+        try!(File::create(path)
+            .and_then(|mut f| f.write_all(contents.as_bytes()))
+            .map_err(|e| ClinkError::WriteFailed(path.clone(), e))
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,238 @@
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use files;
+use ClinkError;
+
+/// The revision of a git repository a dependency asks for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GitReference {
+    Branch(String),
+    Tag(String),
+    Rev(String),
+    DefaultBranch,
+}
+
+/// A git repository a dependency is fetched from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GitSource {
+    pub url: String,
+    pub reference: GitReference,
+}
+
+impl GitSource {
+    pub fn new(url: String, reference: GitReference) -> Self {
+        GitSource {
+            url,
+            reference,
+        }
+    }
+
+    /// A string uniquely describing this source, as stored in Clink.lock.
+    pub fn to_source_string(&self) -> String {
+        match self.reference {
+            GitReference::Branch(ref b) => format!("git+{}?branch={}", self.url, b),
+            GitReference::Tag(ref t) => format!("git+{}?tag={}", self.url, t),
+            GitReference::Rev(ref r) => format!("git+{}?rev={}", self.url, r),
+            GitReference::DefaultBranch => format!("git+{}", self.url),
+        }
+    }
+
+    /// Make sure a checkout of this source is available, and return the commit it's at and the
+    /// folder it's in. If a locked commit is given that commit is checked out, otherwise the
+    /// requested revision is fetched from the remote.
+    pub fn checkout(&self, locked: Option<&str>) -> Result<(String, PathBuf), ClinkError> {
+        // Git would read a URL or revision starting with a dash as an option
+        if self.url.starts_with('-') {
+            return Err(ClinkError::GitFailed(format!("\"{}\" is not a valid repository URL", self.url)));
+        }
+        if self.revision().starts_with('-') {
+            return Err(ClinkError::GitFailed(format!("\"{}\" is not a valid revision", self.revision())));
+        }
+
+        let ident = self.ident();
+        let git_home = files::clone_push_path(&clink_home(), "git");
+
+        // Keep a bare clone of the repository around to fetch into
+        let db = files::clone_push_path(&files::clone_push_path(&git_home, "db"), &ident);
+        let mut fetched = false;
+        if !db.exists() {
+            try!(git(None, &["clone", "--quiet", "--bare", "--", &self.url, &path_str(&db)]));
+            fetched = true;
+        }
+
+        // Use the locked commit if we have it, only going to the remote when we have to
+        let commit = match locked {
+            Some(commit) => {
+                if !fetched && !has_commit(&db, commit) {
+                    try!(self.fetch(&db));
+                }
+                try!(rev_parse(&db, commit))
+            },
+            None => {
+                if !fetched {
+                    try!(self.fetch(&db));
+                }
+                try!(rev_parse(&db, &self.revision()))
+            },
+        };
+
+        // Check out the commit into its own folder, marking it as complete when done so we don't
+        // use half-finished checkouts
+        let mut checkout = files::clone_push_path(&files::clone_push_path(&git_home, "checkouts"), &ident);
+        checkout.push(&commit[..12]);
+        let marker = files::clone_push_path(&checkout, ".clink-ok");
+        if !marker.exists() {
+            let _ = fs::remove_dir_all(&checkout);
+            try!(git(None, &["clone", "--quiet", "--no-checkout", "--", &path_str(&db), &path_str(&checkout)]));
+            try!(git(Some(&checkout), &["checkout", "--quiet", "--detach", &commit]));
+            try!(File::create(&marker).map_err(|e|
+                ClinkError::GitFailed(format!("Could not write {}: {}", marker.display(), e))
+            ));
+        }
+
+        Ok((commit, checkout))
+    }
+
+    fn fetch(&self, db: &Path) -> Result<(), ClinkError> {
+        git(Some(db), &[
+            "fetch", "--quiet", "--force", "--", &self.url, "+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"
+        ]).map(|_| ())
+    }
+
+    fn revision(&self) -> String {
+        match self.reference {
+            GitReference::Branch(ref b) => format!("refs/heads/{}", b),
+            GitReference::Tag(ref t) => format!("refs/tags/{}", t),
+            GitReference::Rev(ref r) => r.clone(),
+            GitReference::DefaultBranch => "HEAD".into(),
+        }
+    }
+
+    /// The folder name for this repository in the cache, readable but unique for every URL.
+    fn ident(&self) -> String {
+        let name = self.url.trim_end_matches('/').rsplit('/').next().unwrap_or("")
+            .trim_end_matches(".git");
+        let name = if name.is_empty() { "repository" } else { name };
+        format!("{}-{}", name, &files::hash_hex(self.url.as_bytes())[..16])
+    }
+}
+
+/// The folder clink keeps per-user data in, ~/.clink unless CLINK_HOME is set.
+pub fn clink_home() -> PathBuf {
+    if let Some(home) = env::var_os("CLINK_HOME") {
+        return PathBuf::from(home);
+    }
+
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).unwrap_or_default();
+    files::clone_push_path(&PathBuf::from(home), ".clink")
+}
+
+fn has_commit(db: &Path, commit: &str) -> bool {
+    git(Some(db), &["cat-file", "-e", &format!("{}^{{commit}}", commit)]).is_ok()
+}
+
+fn rev_parse(db: &Path, revision: &str) -> Result<String, ClinkError> {
+    let output = try!(git(Some(db), &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", revision)])
+        .map_err(|_| ClinkError::GitFailed(format!("Could not find revision \"{}\"", revision)))
+    );
+    Ok(output.trim().into())
+}
+
+/// Run git, returning what it printed if it succeeded.
+fn git(dir: Option<&Path>, args: &[&str]) -> Result<String, ClinkError> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+
+    let output = try!(command.args(args).output().map_err(|e|
+        ClinkError::GitFailed(format!("Could not run git: {}", e))
+    ));
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(ClinkError::GitFailed(format!(
+            "\"git {}\" failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+fn path_str(path: &Path) -> String {
+    format!("{}", path.display())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::path::{Path, PathBuf};
+    use files::TempDir;
+    use super::{GitReference, GitSource, git};
+
+    /// Commit a file to a repository, returning the new commit.
+    fn commit_file(repo: &Path, name: &str) -> String {
+        File::create(repo.join(name)).unwrap();
+        git(Some(repo), &["add", name]).unwrap();
+        git(Some(repo), &["-c", "user.name=clink", "-c", "user.email=clink@example.com", "commit", "-q", "-m", name])
+            .unwrap();
+        git(Some(repo), &["rev-parse", "HEAD"]).unwrap().trim().into()
+    }
+
+    #[test]
+    fn checkout_rejects_urls_that_look_like_options() {
+        let source = GitSource::new("--upload-pack=touch /tmp/pwned".into(), GitReference::DefaultBranch);
+        assert!(source.checkout(None).is_err());
+        let source = GitSource::new("file:///Math".into(), GitReference::Rev("--output=/tmp/pwned".into()));
+        assert!(source.checkout(None).is_err());
+    }
+
+    #[test]
+    fn checkout_clones_from_file_url_and_reuses_checkouts() {
+        let dir = TempDir::new("git");
+        let work = dir.path().join("work");
+        fs::create_dir_all(&work).unwrap();
+        git(Some(&work), &["init", "-q"]).unwrap();
+        let first = commit_file(&work, "Clink.toml");
+        git(Some(&work), &["tag", "v1"]).unwrap();
+        let bare = dir.path().join("Math.git");
+        git(None, &["clone", "-q", "--bare", &work.display().to_string(), &bare.display().to_string()]).unwrap();
+
+        env::set_var("CLINK_HOME", dir.path().join("home"));
+        let url = format!("file://{}", bare.display());
+        let source = GitSource::new(url.clone(), GitReference::Tag("v1".into()));
+        let (commit, checkout) = source.checkout(None).unwrap();
+        assert_eq!(commit, first);
+        assert!(checkout.join("Clink.toml").is_file());
+        assert!(checkout.join(".clink-ok").is_file());
+
+        // A finished checkout is used as it is
+        File::create(checkout.join("untracked")).unwrap();
+        assert_eq!(source.checkout(Some(&commit)).unwrap(), (commit.clone(), checkout.clone()));
+        assert!(checkout.join("untracked").is_file());
+
+        // Without the marker it's checked out again
+        fs::remove_file(checkout.join(".clink-ok")).unwrap();
+        source.checkout(Some(&commit)).unwrap();
+        assert!(!checkout.join("untracked").exists());
+        assert!(checkout.join(".clink-ok").is_file());
+
+        // A locked commit that isn't in the cache yet is fetched
+        let second = commit_file(&work, "README.md");
+        git(Some(&work), &["push", "-q", &bare.display().to_string(), "HEAD:refs/heads/later"]).unwrap();
+        let later = GitSource::new(url, GitReference::Branch("later".into()));
+        let (commit, checkout) = later.checkout(Some(&second)).unwrap();
+        assert_eq!(commit, second);
+        assert!(checkout.join("README.md").is_file());
+
+        // Paths starting with a dash are still paths, git gets them after "--"
+        let relative = PathBuf::from(format!("-clink-git-test-{}", ::std::process::id()));
+        env::set_var("CLINK_HOME", &relative);
+        let checked_out = source.checkout(None).map(|(_, checkout)| checkout.join(".clink-ok").is_file());
+        let _ = fs::remove_dir_all(&relative);
+        env::remove_var("CLINK_HOME");
+        assert!(checked_out.unwrap());
+    }
+}
@@ -0,0 +1,877 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::slice;
+use semver::Version;
+use visualstudio::ProjFiles;
+use cfg::{Cfg, Platform};
+use dependency::{self, Dependency, DependencySource};
+use features::FeatureSelection;
+use files;
+use lockfile::{Lockfile, LockedPackage};
+use wincanonicalize::wincanonicalize;
+use project::{Project, ProjectClass};
+use settings::BuildSettings;
+use target::Target;
+use workspace::Workspace;
+use ClinkError;
+
+/// A project in a resolved graph, with everything a generator needs to write it out.
+#[derive(Clone, Debug)]
+pub struct ResolvedProject {
+    pub name: String,
+    pub version: Option<Version>,
+    pub class: ProjectClass,
+    /// The project a target such as a binary or test belongs to, None for projects themselves.
+    pub package: Option<String>,
+    pub path: PathBuf,
+    pub compile_files: Vec<PathBuf>,
+    pub include_files: Vec<PathBuf>,
+    pub include_paths: Vec<PathBuf>,
+    pub settings: BuildSettings,
+    /// The features enabled on the project.
+    pub features: Vec<String>,
+    /// Names of the projects this project depends on, all of which come before it in the graph.
+    pub dependencies: Vec<String>,
+    /// Projects this project only depends on on some platforms, these also come before it.
+    pub platform_dependencies: Vec<(Cfg, String)>,
+    /// Include folders of those dependencies, only on the platforms using them.
+    pub platform_include_paths: Vec<(Cfg, PathBuf)>,
+    /// Build settings that only apply on some platforms, on top of `settings`.
+    pub platform_settings: Vec<(Cfg, BuildSettings)>,
+    /// If the project is in clink's shared checkout of a git dependency, which generators
+    /// shouldn't write into.
+    pub git_checkout: bool,
+}
+
+impl ResolvedProject {
+    /// The build settings of this project on one platform.
+    pub fn settings_for(&self, platform: &Platform) -> BuildSettings {
+        merge_platform_settings(&self.settings, &self.platform_settings, platform)
+    }
+
+    /// The include folders of this project on one platform.
+    pub fn include_paths_for(&self, platform: &Platform) -> Vec<&PathBuf> {
+        let mut include_paths: Vec<&PathBuf> = self.include_paths.iter().collect();
+        for (cfg, path) in &self.platform_include_paths {
+            if cfg.matches(platform) && !include_paths.contains(&path) {
+                include_paths.push(path);
+            }
+        }
+        include_paths
+    }
+
+    /// Names of the projects this project depends on on one platform.
+    pub fn dependencies_for(&self, platform: &Platform) -> Vec<&String> {
+        let mut dependencies: Vec<&String> = self.dependencies.iter().collect();
+        for (cfg, dep) in &self.platform_dependencies {
+            if cfg.matches(platform) && !dependencies.contains(&dep) {
+                dependencies.push(dep);
+            }
+        }
+        dependencies
+    }
+
+    /// Check if this project depends on a project directly, on any platform.
+    pub fn depends_on(&self, name: &str) -> bool {
+        self.dependencies.iter().any(|d| d == name) ||
+            self.platform_dependencies.iter().any(|(_, d)| d == name)
+    }
+}
+
+/// One or more root projects and all their dependencies, resolved into a backend-neutral form.
+#[derive(Debug)]
+pub struct ProjectGraph {
+    name: String,
+    path: PathBuf,
+    settings: BuildSettings,
+    platform_settings: Vec<(Cfg, BuildSettings)>,
+    roots: Vec<String>,
+    projects: Vec<ResolvedProject>,
+    lockfile: Lockfile,
+    patched: Vec<String>,
+}
+
+impl ProjectGraph {
+    /// Find the project or workspace a folder belongs to and resolve it with the default features.
+    /// Unlike `Workspace::resolve`, this never writes Clink.lock.
+    pub fn load<P: AsRef<Path>>(start: P) -> Result<Self, ClinkError> {
+        let workspace = try!(Workspace::discover(start));
+        ProjectGraph::resolve_workspace(&workspace, &FeatureSelection::new())
+    }
+
+    /// Walk the dependencies of a root project and resolve all of them, with the default features.
+    pub fn resolve(root: &Project) -> Result<Self, ClinkError> {
+        ProjectGraph::resolve_with_features(root, &FeatureSelection::new())
+    }
+
+    /// Walk the dependencies of a root project and resolve all of them, with the given features
+    /// enabled on the root.
+    pub fn resolve_with_features(root: &Project, features: &FeatureSelection) -> Result<Self, ClinkError> {
+        let previous = try!(Lockfile::load(files::clone_push_path(root.path(), "Clink.lock")));
+        let path = try!(wincanonicalize(root.path()));
+        let mut resolver = Resolver::new(None, path.clone(), previous, root.patches().clone());
+        try!(resolver.resolve_roots(&[root], features));
+
+        Ok(ProjectGraph {
+            name: root.name().clone(),
+            path,
+            settings: root.settings().clone(),
+            platform_settings: root.platform_settings().clone(),
+            roots: vec!(root.name().clone()),
+            projects: resolver.projects,
+            lockfile: resolver.lockfile,
+            patched: resolver.patched,
+        })
+    }
+
+    /// Resolve all members of a workspace and their dependencies into one graph, with the given
+    /// features enabled on the members.
+    pub fn resolve_workspace(workspace: &Workspace, features: &FeatureSelection) -> Result<Self, ClinkError> {
+        let roots: Vec<&Project> = workspace.members().iter().collect();
+        let previous = try!(Lockfile::load(files::clone_push_path(workspace.path(), "Clink.lock")));
+        let path = try!(wincanonicalize(workspace.path()));
+        let mut resolver = Resolver::new(Some(workspace), path.clone(), previous, workspace.patches().clone());
+        try!(resolver.resolve_roots(&roots, features));
+
+        Ok(ProjectGraph {
+            name: workspace.name().clone(),
+            path,
+            settings: workspace.settings().clone(),
+            platform_settings: Vec::new(),
+            roots: roots.iter().map(|r| r.name().clone()).collect(),
+            projects: resolver.projects,
+            lockfile: resolver.lockfile,
+            patched: resolver.patched,
+        })
+    }
+
+    /// The name of the root project or workspace the graph was resolved from.
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// The folder of the root project or workspace, output for the whole graph goes here.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// The build settings of the root project or workspace.
+    pub fn settings(&self) -> &BuildSettings {
+        &self.settings
+    }
+
+    /// Names of the projects the graph was resolved from.
+    pub fn roots(&self) -> &Vec<String> {
+        &self.roots
+    }
+
+    /// All projects in the graph, dependencies always come before the projects that depend on them.
+    pub fn projects(&self) -> &Vec<ResolvedProject> {
+        &self.projects
+    }
+
+    /// Iterate over all projects in the graph, dependencies before their dependents.
+    pub fn iter(&self) -> slice::Iter<'_, ResolvedProject> {
+        self.projects.iter()
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ResolvedProject> {
+        self.projects.iter().find(|p| p.name == name)
+    }
+
+    /// The projects a project depends on directly on any platform, in graph order.
+    pub fn dependencies_of(&self, name: &str) -> Vec<&ResolvedProject> {
+        match self.find(name) {
+            Some(project) => self.projects.iter().filter(|p| project.depends_on(&p.name)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The projects that depend on a project directly on any platform, in graph order.
+    pub fn dependents_of(&self, name: &str) -> Vec<&ResolvedProject> {
+        self.projects.iter().filter(|p| p.depends_on(name)).collect()
+    }
+
+    /// Names of the packages that came from a [patch] entry instead of their own dependency.
+    pub fn patched(&self) -> &Vec<String> {
+        &self.patched
+    }
+
+    /// The graph as it's built on one platform. Dependencies and settings for other platforms
+    /// are left out, as are projects only used through those dependencies.
+    pub fn for_platform(&self, platform: &Platform) -> ProjectGraph {
+        let mut projects: Vec<ResolvedProject> = self.projects.iter().cloned().map(|mut project| {
+            for (cfg, dep) in project.platform_dependencies.drain(..) {
+                if cfg.matches(platform) && !project.dependencies.contains(&dep) {
+                    project.dependencies.push(dep);
+                }
+            }
+            for (cfg, path) in project.platform_include_paths.drain(..) {
+                if cfg.matches(platform) && !project.include_paths.contains(&path) {
+                    project.include_paths.push(path);
+                }
+            }
+            project.settings = merge_platform_settings(&project.settings, &project.platform_settings, platform);
+            project.platform_settings.clear();
+            project
+        }).collect();
+
+        // Only keep the roots, their targets and projects that something still depends on,
+        // projects come after their dependencies so going backwards we always see dependents first
+        let mut used: HashSet<String> = self.projects.iter()
+            .filter(|p| self.roots.contains(&p.name) || !self.projects.iter().any(|o|
+                o.dependencies.contains(&p.name) || o.platform_dependencies.iter().any(|(_, d)| d == &p.name)
+            ))
+            .map(|p| p.name.clone())
+            .collect();
+        for project in projects.iter().rev() {
+            if used.contains(&project.name) {
+                used.extend(project.dependencies.iter().cloned());
+            }
+        }
+        projects.retain(|p| used.contains(&p.name));
+
+        ProjectGraph {
+            name: self.name.clone(),
+            path: self.path.clone(),
+            settings: merge_platform_settings(&self.settings, &self.platform_settings, platform),
+            platform_settings: Vec::new(),
+            roots: self.roots.clone(),
+            projects,
+            lockfile: self.lockfile.clone(),
+            patched: self.patched.clone(),
+        }
+    }
+
+    /// Every package in the graph and the exact source it was resolved to, to be stored in
+    /// Clink.lock.
+    pub fn lockfile(&self) -> &Lockfile {
+        &self.lockfile
+    }
+
+    /// Check if the graph's lock file is different from the one at a path, or if there is none.
+    pub fn lockfile_changed<P: AsRef<Path>>(&self, lock_path: P) -> Result<bool, ClinkError> {
+        Ok(match try!(Lockfile::load(lock_path)) {
+            Some(ref previous) => previous != &self.lockfile,
+            None => !self.lockfile.is_empty(),
+        })
+    }
+
+    /// Write the graph's lock file to a path if it changed. If locked, fail instead of changing
+    /// it.
+    pub fn update_lockfile<P: AsRef<Path>>(&self, lock_path: P, locked: bool) -> Result<(), ClinkError> {
+        let lock_path = lock_path.as_ref();
+        if locked && !lock_path.exists() {
+            return Err(ClinkError::InvalidProjectStructure(
+                lock_path.to_path_buf(), "Clink.lock does not exist, but --locked was passed".into()
+            ));
+        }
+
+        if try!(self.lockfile_changed(lock_path)) {
+            if locked {
+                return Err(ClinkError::InvalidProjectStructure(
+                    lock_path.to_path_buf(), "Clink.lock needs to be updated, but --locked was passed".into()
+                ));
+            }
+            try!(self.lockfile.write_to(lock_path));
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a ProjectGraph {
+    type Item = &'a ResolvedProject;
+    type IntoIter = slice::Iter<'a, ResolvedProject>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.projects.iter()
+    }
+}
+
+struct Resolver<'a> {
+    workspace: Option<&'a Workspace>,
+    /// The folder Clink.lock is in, paths in it are relative to this.
+    lock_root: PathBuf,
+    projects: Vec<ResolvedProject>,
+    /// The project that first depended on a package, to explain conflicts.
+    required_by: HashMap<String, String>,
+    /// The features enabled on every package.
+    features: HashMap<String, BTreeSet<String>>,
+    /// The include folders a project passes on to projects depending on it.
+    /// Include folders projects pass on to their dependents, and the platforms they do so on.
+    exported_includes: HashMap<String, Vec<(Option<Cfg>, PathBuf)>>,
+    /// The lock file from the previous resolve, git dependencies stay at the commits in it.
+    previous: Option<Lockfile>,
+    lockfile: Lockfile,
+    /// Git sources that have already been checked out, and where.
+    checkouts: HashMap<String, PathBuf>,
+    /// The source and commit of every git checkout, by the folder it's in.
+    git_commits: HashMap<PathBuf, (String, String)>,
+    /// Replacements for dependencies from the root manifest's [patch] table.
+    patches: Vec<Dependency>,
+    /// Names of the packages a patch was used for.
+    patched: Vec<String>,
+    /// The chain of projects currently being resolved, to detect dependency cycles.
+    resolving: Vec<String>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(
+        workspace: Option<&'a Workspace>, lock_root: PathBuf, previous: Option<Lockfile>, patches: Vec<Dependency>
+    ) -> Self {
+        Resolver {
+            workspace,
+            lock_root,
+            projects: Vec::new(),
+            required_by: HashMap::new(),
+            features: HashMap::new(),
+            exported_includes: HashMap::new(),
+            previous,
+            lockfile: Lockfile::new(),
+            checkouts: HashMap::new(),
+            git_commits: HashMap::new(),
+            patches,
+            patched: Vec::new(),
+            resolving: Vec::new(),
+        }
+    }
+
+    fn resolve_roots(&mut self, roots: &[&Project], selection: &FeatureSelection) -> Result<(), ClinkError> {
+        // Features change which dependencies are used, so all of them need to be known first
+        try!(self.select_root_features(roots, selection));
+        loop {
+            let before = self.features.clone();
+            let mut visited = HashSet::new();
+            for root in roots {
+                try!(self.request_features(root, &mut visited));
+                if needs_dev_dependencies(root) {
+                    for (dep, features) in root.enabled_dev_dependencies() {
+                        try!(self.request_dependency_features(root.name(), dep, features, &mut visited));
+                    }
+                }
+            }
+            if self.features == before {
+                break;
+            }
+        }
+
+        for root in roots {
+            // A root may already have been resolved as the dependency of another root
+            if self.find(root.name()).is_none() {
+                try!(self.resolve_recursive(root));
+            }
+        }
+
+        // Tests and examples of the roots can also use their dev-dependencies
+        for root in roots.iter().filter(|r| needs_dev_dependencies(r)) {
+            for dep in root.dev_dependencies().iter().filter(|d| !d.is_external()) {
+                try!(self.resolve_dependency(root, dep));
+            }
+        }
+
+        // Only the roots' own targets are part of the graph, not those of their dependencies
+        for root in roots {
+            let package = try!(self.find(root.name()).cloned().ok_or_else(|| ClinkError::InvalidProjectFile(
+                format!("Project \"{}\" could not be resolved", root.name())
+            )));
+            for target in root.targets() {
+                if self.find(&target.name).is_some() {
+                    return Err(ClinkError::InvalidProjectFile(
+                        format!("Target \"{}\" has the same name as another project", target.name)
+                    ));
+                }
+                let target = try!(self.resolve_target(root, &package, target));
+                self.projects.push(target);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Enable the default features and the features selected on the command line on the roots.
+    fn select_root_features(&mut self, roots: &[&Project], selection: &FeatureSelection) -> Result<(), ClinkError> {
+        for root in roots {
+            let mut requested = BTreeSet::new();
+            requested.insert("default".to_string());
+            for feature in root.features() {
+                if selection.all_features || selection.features.contains(&feature.name) {
+                    requested.insert(feature.name.clone());
+                }
+            }
+            self.features.insert(root.name().clone(), requested);
+        }
+
+        for name in &selection.features {
+            if !roots.iter().any(|r| r.features().iter().any(|f| &f.name == name)) {
+                return Err(ClinkError::InvalidProjectFile(
+                    format!("None of the selected projects have a feature named \"{}\"", name)
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pass on the features a project requests from its dependencies, going through the graph
+    /// once. This has to be repeated until nothing changes anymore, since a project visited
+    /// early can still get more features from a project visited later.
+    fn request_features(&mut self, project: &Project, visited: &mut HashSet<String>) -> Result<(), ClinkError> {
+        if !visited.insert(project.name().clone()) {
+            return Ok(());
+        }
+
+        let requested = self.features.get(project.name()).cloned().unwrap_or_default();
+        let enabled = try!(project.enabled_features(&requested));
+        self.features.insert(project.name().clone(), enabled.clone());
+
+        for (dep, features) in project.enabled_dependencies(&enabled) {
+            try!(self.request_dependency_features(project.name(), dep, features, visited));
+        }
+
+        Ok(())
+    }
+
+    fn request_dependency_features(
+        &mut self, dependent: &str, dep: &Dependency, features: BTreeSet<String>, visited: &mut HashSet<String>
+    ) -> Result<(), ClinkError> {
+        if dep.is_external() {
+            return Ok(());
+        }
+
+        self.features.entry(dep.package().clone()).or_default().extend(features);
+        let path = try!(self.dependency_path(dep));
+        let proj = try!(dep.open_at(dependent, &path));
+        self.request_features(&proj, visited)
+    }
+
+    /// The dependencies of a project that are part of the graph with its enabled features.
+    fn used_dependencies(&self, project: &Project) -> Vec<Dependency> {
+        // TODO: Implement external dependencies
+        let enabled = self.features.get(project.name()).cloned().unwrap_or_default();
+        project.enabled_dependencies(&enabled).into_iter()
+            .map(|(dep, _)| dep)
+            .filter(|d| !d.is_external())
+            .cloned()
+            .collect()
+    }
+
+    fn resolve_recursive(&mut self, project: &Project) -> Result<(), ClinkError> {
+        // Go over all dependencies
+        self.resolving.push(project.name().clone());
+        for dep in &self.used_dependencies(project) {
+            try!(self.resolve_dependency(project, dep));
+        }
+        self.resolving.pop();
+
+        // Resolve this project now that all its dependencies are available, workspace members
+        // inherit the workspace's settings
+        let mut resolved = try!(self.resolve_project(project));
+        if let Some(workspace) = self.workspace {
+            if workspace.is_member(&resolved.path) {
+                resolved.settings = resolved.settings.inherit(workspace.settings());
+            }
+        }
+        self.lock_package(project, &resolved.path);
+        self.projects.push(resolved);
+
+        Ok(())
+    }
+
+    /// Resolve a dependency of a project and everything it depends on, if it wasn't already.
+    fn resolve_dependency(&mut self, project: &Project, dep: &Dependency) -> Result<(), ClinkError> {
+        let path = try!(self.dependency_path(dep));
+
+        // Check if this dependency has already been resolved
+        if let Some(found) = self.find(dep.package()) {
+            // It exists already, make sure it's the same project in a version we accept
+            let other = self.required_by.get(dep.package())
+                .map(|n| format!("\"{}\"", n))
+                .unwrap_or_else(|| "the workspace".into());
+            if path != found.path {
+                return Err(ClinkError::InvalidProjectStructure(path, format!(
+                    "\"{}\" depends on \"{}\" from this folder, but {} depends on it from {}",
+                    project.name(), dep.package(), other, found.path.display()
+                )));
+            }
+            if !dep.accepts(found.version.as_ref()) {
+                return Err(ClinkError::InvalidProjectStructure(path, format!(
+                    "\"{}\" requires version {} of \"{}\", but {} depends on it and found {}",
+                    project.name(), dep.version().unwrap(), dep.package(), other,
+                    dependency::version_string(found.version.as_ref())
+                )));
+            }
+
+            return Ok(());
+        }
+        // A project that is still being resolved can't depend on itself through its dependencies
+        if let Some(index) = self.resolving.iter().position(|n| n == dep.package()) {
+            let mut cycle = self.resolving[index..].to_vec();
+            cycle.push(dep.package().clone());
+            return Err(ClinkError::InvalidProjectStructure(
+                path, format!("Dependency cycle: {}", cycle.join(" -> "))
+            ));
+        }
+        self.required_by.insert(dep.package().clone(), project.name().clone());
+
+        // Open the project and resolve it as well
+        let proj = try!(dep.open_at(project.name(), &path));
+        self.resolve_recursive(&proj)
+    }
+
+    /// Record where a package in the graph came from in the new lock file.
+    fn lock_package(&mut self, project: &Project, path: &Path) {
+        let mut package = LockedPackage {
+            name: project.name().clone(),
+            path: None,
+            source: None,
+            commit: None,
+            checksum: project.checksum().clone(),
+        };
+
+        match self.git_commits.get(path) {
+            Some((source, commit)) => {
+                package.source = Some(source.clone());
+                package.commit = Some(commit.clone());
+            },
+            None => {
+                let relative = files::relative_path(&self.lock_root, path);
+                package.path = Some(relative.to_string_lossy().replace('\\', "/"));
+            },
+        }
+
+        self.lockfile.add(package);
+    }
+
+    /// Find the folder a dependency's project is in, checking out git dependencies if needed.
+    fn dependency_path(&mut self, dep: &Dependency) -> Result<PathBuf, ClinkError> {
+        // A patch replaces where the dependency comes from
+        let patch = self.patches.iter().find(|p| p.name() == dep.package()).map(|p| p.source().clone());
+        if patch.is_some() && !self.patched.contains(dep.package()) {
+            self.patched.push(dep.package().clone());
+        }
+
+        let source = patch.unwrap_or_else(|| dep.source().clone());
+        let git = match source {
+            DependencySource::Path(path) => return if path.is_dir() {
+                Ok(path)
+            } else {
+                Err(ClinkError::MissingDependency(dep.name().clone(), path))
+            },
+            DependencySource::Git(ref git) => git,
+        };
+
+        let source = git.to_source_string();
+        if let Some(path) = self.checkouts.get(&source) {
+            return Ok(path.clone());
+        }
+
+        // Stay at the locked commit if there is one
+        let locked = self.previous.as_ref()
+            .and_then(|l| l.find_commit(dep.package(), &source))
+            .map(|c| c.to_string());
+        let (commit, path) = try!(git.checkout(locked.as_deref()));
+        let path = try!(wincanonicalize(path));
+
+        self.git_commits.insert(path.clone(), (source.clone(), commit));
+        self.checkouts.insert(source, path.clone());
+
+        Ok(path)
+    }
+
+    fn resolve_project(&mut self, project: &Project) -> Result<ResolvedProject, ClinkError> {
+        // Find the .hpp and .cpp files of the project
+        let files = try!(project.scan_files());
+
+        // Our own include folder and the include folders of our dependencies are on the include
+        // path, public dependencies also pass on their include folder to us and our dependents
+        let own_include = files::clone_push_path(project.path(), "include");
+        let mut include_paths = vec!(own_include.clone());
+        let mut platform_include_paths = Vec::new();
+        let mut exported_includes = vec!((None, own_include));
+        let mut dependencies = Vec::new();
+        let mut platform_dependencies = Vec::new();
+        for dep in &self.used_dependencies(project) {
+            for (cfg, path) in self.dependency_includes(dep) {
+                add_include_path(&mut include_paths, &mut platform_include_paths, cfg.clone(), path.clone());
+                if dep.is_public() && !exported_includes.contains(&(cfg.clone(), path.clone())) {
+                    exported_includes.push((cfg, path));
+                }
+            }
+
+            match dep.platform() {
+                Some(cfg) => platform_dependencies.push((cfg.clone(), dep.package().clone())),
+                None => dependencies.push(dep.package().clone()),
+            }
+        }
+        self.exported_includes.insert(project.name().clone(), exported_includes);
+
+        // Enabled features add their defines and sources
+        let features = self.features.get(project.name()).cloned().unwrap_or_default();
+        let mut compile_files = files.compile;
+        let mut include_files = files.include;
+        let mut settings = project.settings().clone();
+        for feature in project.features().iter().filter(|f| features.contains(&f.name)) {
+            settings.defines.extend(feature.defines.iter().cloned());
+            for source in &feature.sources {
+                let feature_files = try!(ProjFiles::scan(source, &[]));
+                compile_files.extend(feature_files.compile);
+                include_files.extend(feature_files.include);
+            }
+        }
+
+        let path = try!(wincanonicalize(project.path()));
+        Ok(ResolvedProject {
+            name: project.name().clone(),
+            version: project.version().cloned(),
+            class: project.class().clone(),
+            package: None,
+            git_checkout: self.git_commits.contains_key(&path),
+            path,
+            compile_files,
+            include_files,
+            include_paths,
+            platform_include_paths,
+            settings,
+            features: features.into_iter().collect(),
+            dependencies,
+            platform_dependencies,
+            platform_settings: project.platform_settings().clone(),
+        })
+    }
+
+    /// Resolve a target of a project, such as a binary or test, into a project of its own.
+    fn resolve_target(
+        &self, project: &Project, package: &ResolvedProject, target: &Target
+    ) -> Result<ResolvedProject, ClinkError> {
+        let files = try!(ProjFiles::scan(&target.path, &[]));
+
+        // The target gets access to everything the project itself can use, and links to its library
+        let mut dependencies = package.dependencies.clone();
+        if package.class == ProjectClass::Library {
+            dependencies.insert(0, package.name.clone());
+        }
+        let mut platform_dependencies = package.platform_dependencies.clone();
+        let mut include_paths = package.include_paths.clone();
+        let mut platform_include_paths = package.platform_include_paths.clone();
+
+        // Tests and examples also get the dev-dependencies
+        if target.kind.uses_dev_dependencies() {
+            for dep in project.dev_dependencies().iter().filter(|d| !d.is_external()) {
+                for (cfg, path) in self.dependency_includes(dep) {
+                    add_include_path(&mut include_paths, &mut platform_include_paths, cfg, path);
+                }
+
+                match dep.platform() {
+                    Some(cfg) => platform_dependencies.push((cfg.clone(), dep.package().clone())),
+                    None => if !dependencies.contains(dep.package()) {
+                        dependencies.push(dep.package().clone());
+                    },
+                }
+            }
+        }
+
+        Ok(ResolvedProject {
+            name: target.name.clone(),
+            version: package.version.clone(),
+            class: target.class.clone(),
+            package: Some(package.name.clone()),
+            path: package.path.clone(),
+            compile_files: files.compile,
+            include_files: files.include,
+            include_paths,
+            platform_include_paths,
+            settings: package.settings.clone(),
+            features: package.features.clone(),
+            dependencies,
+            platform_dependencies,
+            platform_settings: package.platform_settings.clone(),
+            git_checkout: package.git_checkout,
+        })
+    }
+
+    /// The include folders a dependency gives us, and the platforms they're only used on. Going
+    /// through a platform dependency limits them to its platform as well.
+    fn dependency_includes(&self, dep: &Dependency) -> Vec<(Option<Cfg>, PathBuf)> {
+        let exported = self.exported_includes.get(dep.package()).cloned().unwrap_or_default();
+        exported.into_iter().map(|(cfg, path)| {
+            let cfg = match (dep.platform(), cfg) {
+                (Some(outer), Some(inner)) => Some(Cfg::All(vec!(outer.clone(), inner))),
+                (Some(outer), None) => Some(outer.clone()),
+                (None, inner) => inner,
+            };
+            (cfg, path)
+        }).collect()
+    }
+
+    fn find(&self, name: &str) -> Option<&ResolvedProject> {
+        self.projects.iter().find(|p| p.name == name)
+    }
+}
+
+/// Add an include folder, on every platform or only on some.
+fn add_include_path(
+    include_paths: &mut Vec<PathBuf>, platform_include_paths: &mut Vec<(Cfg, PathBuf)>, cfg: Option<Cfg>, path: PathBuf
+) {
+    if include_paths.contains(&path) {
+        return;
+    }
+    match cfg {
+        Some(cfg) => if !platform_include_paths.contains(&(cfg.clone(), path.clone())) {
+            platform_include_paths.push((cfg, path));
+        },
+        None => {
+            // Used on every platform now, so the platform entries aren't needed anymore
+            platform_include_paths.retain(|(_, p)| p != &path);
+            include_paths.push(path);
+        },
+    }
+}
+
+/// Check if a root has tests or examples that need its dev-dependencies.
+fn needs_dev_dependencies(root: &Project) -> bool {
+    root.targets().iter().any(|t| t.kind.uses_dev_dependencies())
+}
+
+/// Apply the settings of all conditions matching a platform on top of the base settings.
+fn merge_platform_settings(
+    settings: &BuildSettings, platform_settings: &[(Cfg, BuildSettings)], platform: &Platform
+) -> BuildSettings {
+    platform_settings.iter()
+        .filter(|&(cfg, _)| cfg.matches(platform))
+        .fold(settings.clone(), |merged, (_, extra)| extra.inherit(&merged))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use cfg::Platform;
+    use files::TempDir;
+    use super::ProjectGraph;
+
+    fn write_project(root: &Path, name: &str, manifest: &str) {
+        let path = root.join(name);
+        fs::create_dir_all(&path).unwrap();
+        File::create(path.join("Clink.toml")).unwrap().write_all(manifest.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn load_orders_dependencies_first() {
+        let dir = TempDir::new("graph");
+        let root = dir.path();
+        write_project(root, "Math", "[package]\nname = \"Math\"\ntype = \"library\"\n");
+        write_project(root, "Win", "[package]\nname = \"Win\"\ntype = \"library\"\n");
+        write_project(root, "App", concat!(
+            "[package]\nname = \"App\"\ntype = \"application\"\n",
+            "[dependencies]\nMath = \"../Math\"\n",
+            "[target.'cfg(windows)'.dependencies]\nWin = \"../Win\"\n",
+            "[target.'cfg(windows)'.build]\ndefines = [\"APP_WINDOWS\"]\n",
+        ));
+
+        let graph = ProjectGraph::load(root.join("App")).unwrap();
+
+        let names: Vec<&str> = graph.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names.last(), Some(&"App"));
+        assert_eq!(graph.dependencies_of("App").len(), 2);
+        assert_eq!(graph.dependents_of("Math")[0].name, "App");
+
+        let app = graph.find("App").unwrap();
+        let windows = Platform::new("windows", "x86_64");
+        assert_eq!(app.dependencies_for(&windows).len(), 2);
+        assert_eq!(app.dependencies_for(&Platform::new("linux", "x86_64")).len(), 1);
+        assert_eq!(app.settings_for(&windows).defines, vec!("APP_WINDOWS"));
+    }
+
+    #[test]
+    fn dev_dependencies_are_not_passed_on() {
+        let dir = TempDir::new("graph-dev");
+        let root = dir.path();
+        write_project(root, "T", "[package]\nname = \"T\"\ntype = \"library\"\n");
+        write_project(root, "A", concat!(
+            "[package]\nname = \"A\"\ntype = \"library\"\n",
+            "[dev-dependencies]\nT = \"../T\"\n",
+            "[[test]]\nname = \"ATests\"\npath = \"tests\"\n",
+        ));
+        write_project(root, "B", concat!(
+            "[package]\nname = \"B\"\ntype = \"application\"\n",
+            "[dependencies]\nA = \"../A\"\n",
+        ));
+        fs::create_dir_all(root.join("A").join("tests")).unwrap();
+
+        let graph = ProjectGraph::load(root.join("B")).unwrap();
+        let a_graph = ProjectGraph::load(root.join("A")).unwrap();
+
+        // Only A's own tests use T, when A is the root
+        assert!(a_graph.find("T").is_some());
+        assert!(graph.find("T").is_none());
+        assert!(graph.find("ATests").is_none());
+        assert!(graph.lockfile().packages.iter().all(|p| p.name != "T"));
+
+        let b = graph.find("B").unwrap();
+        assert_eq!(b.dependencies, vec!("A"));
+        assert!(!b.include_paths.iter().any(|p| p.parent().and_then(|p| p.file_name()) == Some("T".as_ref())));
+    }
+
+    #[test]
+    fn patch_replaces_transitive_dependencies() {
+        let dir = TempDir::new("graph-patch");
+        let root = dir.path();
+        write_project(root, "Math", "[package]\nname = \"Math\"\ntype = \"library\"\n");
+        write_project(root, "MathFork", "[package]\nname = \"Math\"\ntype = \"library\"\n");
+        write_project(root, "Engine", concat!(
+            "[package]\nname = \"Engine\"\ntype = \"library\"\n",
+            "[dependencies]\nMath = \"../Math\"\n",
+        ));
+        write_project(root, "App", concat!(
+            "[package]\nname = \"App\"\ntype = \"application\"\n",
+            "[dependencies]\nEngine = \"../Engine\"\n",
+            "[patch]\nMath = { path = \"../MathFork\" }\n",
+        ));
+
+        let graph = ProjectGraph::load(root.join("App")).unwrap();
+        let fork = root.join("MathFork").canonicalize().unwrap();
+
+        assert_eq!(graph.patched(), &vec!("Math".to_string()));
+        assert_eq!(graph.find("Math").unwrap().path, fork);
+        assert!(graph.find("Engine").unwrap().include_paths.contains(&fork.join("include")));
+
+        let locked = graph.lockfile().packages.iter().find(|p| p.name == "Math").unwrap();
+        assert_eq!(locked.path.as_deref(), Some("../MathFork"));
+        assert_eq!(locked.source, None);
+    }
+
+    #[test]
+    fn platform_dependency_includes_stay_on_their_platform() {
+        let dir = TempDir::new("graph-include");
+        let root = dir.path();
+        write_project(root, "Win", "[package]\nname = \"Win\"\ntype = \"library\"\n");
+        write_project(root, "Engine", concat!(
+            "[package]\nname = \"Engine\"\ntype = \"library\"\n",
+            "[target.'cfg(windows)'.dependencies]\nWin = { path = \"../Win\", public = true }\n",
+        ));
+        write_project(root, "App", concat!(
+            "[package]\nname = \"App\"\ntype = \"application\"\n",
+            "[dependencies]\nEngine = \"../Engine\"\n",
+        ));
+
+        let graph = ProjectGraph::load(root.join("App")).unwrap();
+
+        // Win's include folder is passed on through Engine, but only on windows
+        let is_win = |p: &&PathBuf| p.parent().and_then(|p| p.file_name()) == Some("Win".as_ref());
+        let windows = Platform::new("windows", "x86_64");
+        let linux = Platform::new("linux", "x86_64");
+        for name in &["Engine", "App"] {
+            let project = graph.find(name).unwrap();
+            assert!(!project.include_paths.iter().any(|p| is_win(&p)));
+            assert!(project.include_paths_for(&windows).iter().any(is_win));
+            assert!(!project.include_paths_for(&linux).iter().any(is_win));
+        }
+
+        let linux_graph = graph.for_platform(&linux);
+        assert!(!linux_graph.find("App").unwrap().include_paths.iter().any(|p| is_win(&p)));
+        let windows_graph = graph.for_platform(&windows);
+        assert!(windows_graph.find("App").unwrap().include_paths.iter().any(|p| is_win(&p)));
+    }
+}
@@ -1,23 +1,82 @@
+extern crate glob;
+extern crate rustc_serialize;
+extern crate semver;
+extern crate sha1;
 extern crate toml;
 extern crate uuid;
 extern crate walkdir;
 extern crate xml;
 
 mod visualstudio;
+mod cfg;
+mod check;
 mod dependency;
+mod diff;
+mod edit;
+mod features;
 mod files;
+mod generator;
+mod git;
+mod graph;
+mod lockfile;
+mod metadata;
+mod native;
 mod project;
+mod scaffold;
+mod settings;
+mod target;
 mod tomlvalue;
+mod tree;
 mod wincanonicalize;
+mod workspace;
 
+use std::error::Error;
 use std::fmt::{self, Display, Formatter};
+use std::io;
 use std::path::PathBuf;
 
-pub use project::Project;
+pub use cfg::{Cfg, Platform};
+pub use check::{CheckReport, Diagnostic, Severity, check_manifests};
+pub use dependency::{Dependency, DependencySource};
+pub use diff::unified_diff;
+pub use edit::{add_dependency, remove_dependency};
+pub use features::{Feature, FeatureSelection};
+pub use generator::{FileComparison, FileStatus, GeneratedFiles, Generator, compare_files, write_files};
+pub use git::{GitReference, GitSource};
+pub use graph::{ProjectGraph, ResolvedProject};
+pub use lockfile::{Lockfile, LockedPackage};
+pub use metadata::{METADATA_FORMAT_VERSION, render_metadata};
+pub use native::{NativeBuilder, Profile, Toolchain};
+pub use project::{Project, ProjectBuilder, ProjectClass};
+pub use scaffold::{NewProject, find_template, init_project, new_project};
+pub use settings::BuildSettings;
+pub use target::{Target, TargetKind};
+pub use tree::{render_dot, render_inverted_tree, render_tree};
+pub use visualstudio::{
+    ProjDesc, ProjFiles, SlnFile, VcxprojFile, VcxprojPlatform, VcxprojType, VisualStudioGenerator, render_filters
+};
+pub use workspace::{Workspace, find_project_path, find_workspace_root};
 
+#[derive(Debug)]
 pub enum ClinkError {
     InvalidProjectStructure(PathBuf, String), // Project location, Error string
     InvalidProjectFile(String),
+    BuildFailed(String),
+    GitFailed(String),
+    /// A file or folder could not be read.
+    Io(PathBuf, io::Error),
+    /// A toml file could not be parsed.
+    TomlSyntax {
+        path: PathBuf,
+        /// Line and column of the error, starting at 1.
+        line: usize,
+        column: usize,
+        message: String,
+        /// The line of the file the error is on.
+        snippet: String,
+    },
+    MissingDependency(String, PathBuf), // Dependency name, Folder that does not exist
+    WriteFailed(PathBuf, io::Error),
 }
 
 impl Display for ClinkError {
@@ -27,6 +86,31 @@ impl Display for ClinkError {
                 write!(f, "Invalid project structure\n Location: {}\n Error: {}", loc.display(), msg),
             ClinkError::InvalidProjectFile(ref msg) =>
                 write!(f, "Invalid project file\n {}", msg),
+            ClinkError::BuildFailed(ref msg) =>
+                write!(f, "Build failed\n {}", msg),
+            ClinkError::GitFailed(ref msg) =>
+                write!(f, "Git operation failed\n {}", msg),
+            ClinkError::Io(ref path, ref err) =>
+                write!(f, "Could not read {}\n {}", path.display(), err),
+            ClinkError::TomlSyntax { ref path, line, column, ref message, ref snippet } =>
+                write!(f, "Invalid toml in {}:{}:{}\n {}\n {}\n {}^",
+                    path.display(), line, column, message, snippet, " ".repeat(column - 1)
+                ),
+            ClinkError::MissingDependency(ref name, ref path) =>
+                write!(f, "Could not find dependency \"{}\"\n Location: {}\n Error: Folder does not exist",
+                    name, path.display()
+                ),
+            ClinkError::WriteFailed(ref path, ref err) =>
+                write!(f, "Could not write {}\n {}", path.display(), err),
+        }
+    }
+}
+
+impl Error for ClinkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ClinkError::Io(_, ref err) | ClinkError::WriteFailed(_, ref err) => Some(err),
+            _ => None,
         }
     }
 }
@@ -0,0 +1,148 @@
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use toml::{Table, Value};
+use tomlvalue::{parse_toml, toml_table, toml_value_str};
+use ClinkError;
+
+/// A package in Clink.lock, recording exactly what a project in the graph resolved to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LockedPackage {
+    pub name: String,
+    /// The folder of a path dependency, relative to the lock file.
+    pub path: Option<String>,
+    /// Where a git dependency came from.
+    pub source: Option<String>,
+    /// The commit a git dependency was checked out at.
+    pub commit: Option<String>,
+    /// Hash of the package's Clink.toml.
+    pub checksum: String,
+}
+
+/// The contents of a Clink.lock file, written next to the root manifest so everyone generates
+/// against the same sources.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lockfile {
+    pub packages: Vec<LockedPackage>,
+}
+
+impl Lockfile {
+    pub fn new() -> Self {
+        Lockfile::default()
+    }
+
+    /// Read a lock file, returns None if there isn't one.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Self>, ClinkError> {
+        let path = path.as_ref();
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(_) => return Ok(None),
+        };
+        let mut toml_str = String::new();
+        try!(file.read_to_string(&mut toml_str).map_err(|e| ClinkError::Io(path.to_path_buf(), e)));
+
+        let toml = try!(parse_toml(path, &toml_str));
+        Lockfile::from_toml(&toml).map(Some)
+    }
+
+    pub fn parse(toml_str: &str) -> Result<Self, ClinkError> {
+        let toml = try!(parse_toml(Path::new("Clink.lock"), toml_str));
+        Lockfile::from_toml(&toml)
+    }
+
+    fn from_toml(toml: &Table) -> Result<Self, ClinkError> {
+
+        let mut packages = Vec::new();
+        if let Some(entries) = toml.get("package").and_then(Value::as_slice) {
+            for entry in entries {
+                let entry = try!(toml_table(entry, "package"));
+                packages.push(LockedPackage {
+                    name: try!(toml_value_str(entry, "name")).into(),
+                    path: entry.get("path").and_then(Value::as_str).map(|p| p.into()),
+                    source: entry.get("source").and_then(Value::as_str).map(|s| s.into()),
+                    commit: entry.get("commit").and_then(Value::as_str).map(|c| c.into()),
+                    checksum: entry.get("checksum").and_then(Value::as_str).unwrap_or("").into(),
+                });
+            }
+        }
+
+        Ok(Lockfile {
+            packages,
+        })
+    }
+
+    /// Find the commit a package from a source was locked to.
+    pub fn find_commit(&self, name: &str, source: &str) -> Option<&str> {
+        self.packages.iter()
+            .find(|p| p.name == name && p.source.as_deref() == Some(source))
+            .and_then(|p| p.commit.as_deref())
+    }
+
+    pub fn add(&mut self, package: LockedPackage) {
+        self.packages.push(package);
+        self.packages.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<(), ClinkError> {
+        let path = path.as_ref();
+        File::create(path)
+            .and_then(|mut file| file.write_all(self.to_string().as_bytes()))
+            .map_err(|e| ClinkError::WriteFailed(path.to_path_buf(), e))
+    }
+}
+
+impl Display for Lockfile {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        try!(writeln!(f, "# This file is generated by clink, do not edit it by hand."));
+
+        for package in &self.packages {
+            try!(writeln!(f, "\n[[package]]"));
+            try!(writeln!(f, "name = {}", Value::String(package.name.clone())));
+            if let Some(ref path) = package.path {
+                try!(writeln!(f, "path = {}", Value::String(path.clone())));
+            }
+            if let Some(ref source) = package.source {
+                try!(writeln!(f, "source = {}", Value::String(source.clone())));
+            }
+            if let Some(ref commit) = package.commit {
+                try!(writeln!(f, "commit = {}", Value::String(commit.clone())));
+            }
+            try!(writeln!(f, "checksum = {}", Value::String(package.checksum.clone())));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lockfile, LockedPackage};
+
+    #[test]
+    fn to_string_round_trips() {
+        let mut lock = Lockfile::new();
+        lock.add(LockedPackage {
+            name: "Zlib".into(),
+            path: None,
+            source: Some("git+file:///tmp/zlib?tag=v1.2".into()),
+            commit: Some("0123456789abcdef0123456789abcdef01234567".into()),
+            checksum: "89abcdef".into(),
+        });
+        lock.add(LockedPackage {
+            name: "Engine".into(),
+            path: Some("../Engine".into()),
+            source: None,
+            commit: None,
+            checksum: "01234567".into(),
+        });
+
+        let parsed = Lockfile::parse(&lock.to_string()).ok().unwrap();
+        assert_eq!(parsed, lock);
+        assert_eq!(parsed.packages[0].name, "Engine");
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use rustc_serialize::json::Json;
+use files;
+use graph::{ProjectGraph, ResolvedProject};
+use native::{NativeBuilder, Profile};
+use visualstudio::VisualStudioGenerator;
+
+/// The version of the metadata format, this goes up whenever existing fields change meaning or
+/// are removed. New fields can be added without changing it.
+pub const METADATA_FORMAT_VERSION: u64 = 1;
+
+/// Describe a resolved graph as JSON, for tools that need to know about a project without reading
+/// its Clink.toml files themselves.
+pub fn render_metadata(graph: &ProjectGraph) -> String {
+    let mut root = BTreeMap::new();
+    root.insert("format_version".into(), Json::U64(METADATA_FORMAT_VERSION));
+    root.insert("name".into(), Json::String(graph.name().clone()));
+    root.insert("path".into(), path_json(graph.path()));
+    root.insert("roots".into(), strings_json(graph.roots()));
+    root.insert("solution".into(), path_json(&files::clone_push_path(graph.path(), &format!("{}.sln", graph.name()))));
+    root.insert("patched".into(), strings_json(graph.patched()));
+    root.insert("projects".into(), Json::Array(graph.projects().iter().map(|p| project_json(graph, p)).collect()));
+
+    format!("{}\n", Json::Object(root).pretty())
+}
+
+fn project_json(graph: &ProjectGraph, project: &ResolvedProject) -> Json {
+    let mut object = BTreeMap::new();
+    object.insert("name".into(), Json::String(project.name.clone()));
+    object.insert("version".into(), project.version.as_ref()
+        .map(|v| Json::String(v.to_string()))
+        .unwrap_or(Json::Null)
+    );
+    object.insert("kind".into(), Json::String(project.class.to_string()));
+    object.insert("path".into(), path_json(&project.path));
+    object.insert("manifest_path".into(), path_json(&files::clone_push_path(&project.path, "Clink.toml")));
+    object.insert("features".into(), strings_json(&project.features));
+    object.insert("defines".into(), strings_json(&project.settings.defines));
+    object.insert("include_paths".into(), paths_json(&project.include_paths));
+    object.insert("compile_files".into(), paths_json(&project.compile_files));
+    object.insert("include_files".into(), paths_json(&project.include_files));
+
+    // Dependencies only used on some platforms have their condition, the others have none
+    let mut dependencies: Vec<Json> = project.dependencies.iter()
+        .map(|d| dependency_json(d, None))
+        .collect();
+    dependencies.extend(project.platform_dependencies.iter()
+        .map(|(cfg, d)| dependency_json(d, Some(format!("cfg({})", cfg))))
+    );
+    object.insert("dependencies".into(), Json::Array(dependencies));
+
+    object.insert("platform_defines".into(), Json::Array(project.platform_settings.iter()
+        .map(|(cfg, settings)| {
+            let mut entry = BTreeMap::new();
+            entry.insert("platform".into(), Json::String(format!("cfg({})", cfg)));
+            entry.insert("defines".into(), strings_json(&settings.defines));
+            Json::Object(entry)
+        })
+        .collect()
+    ));
+
+    object.insert("platform_include_paths".into(), Json::Array(project.platform_include_paths.iter()
+        .map(|(cfg, path)| {
+            let mut entry = BTreeMap::new();
+            entry.insert("platform".into(), Json::String(format!("cfg({})", cfg)));
+            entry.insert("path".into(), path_json(path));
+            Json::Object(entry)
+        })
+        .collect()
+    ));
+
+    // The files clink writes for this project
+    let mut generated = BTreeMap::new();
+    let folder = VisualStudioGenerator::project_folder(graph, project);
+    generated.insert("vcxproj".into(),
+        path_json(&files::clone_push_path(&folder, &format!("{}.vcxproj", project.name)))
+    );
+    generated.insert("vcxproj_filters".into(),
+        path_json(&files::clone_push_path(&folder, &format!("{}.vcxproj.filters", project.name)))
+    );
+    generated.insert("debug".into(), path_json(&NativeBuilder::new(Profile::Debug).artifact_path(graph, project)));
+    generated.insert("release".into(), path_json(&NativeBuilder::new(Profile::Release).artifact_path(graph, project)));
+    object.insert("generated".into(), Json::Object(generated));
+
+    Json::Object(object)
+}
+
+fn dependency_json(name: &str, platform: Option<String>) -> Json {
+    let mut object = BTreeMap::new();
+    object.insert("name".into(), Json::String(name.into()));
+    object.insert("platform".into(), platform.map(Json::String).unwrap_or(Json::Null));
+    Json::Object(object)
+}
+
+fn path_json(path: &Path) -> Json {
+    Json::String(path.display().to_string())
+}
+
+fn paths_json<P: AsRef<Path>>(paths: &[P]) -> Json {
+    Json::Array(paths.iter().map(|p| path_json(p.as_ref())).collect())
+}
+
+fn strings_json(strings: &[String]) -> Json {
+    Json::Array(strings.iter().map(|s| Json::String(s.clone())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Write;
+    use rustc_serialize::json::Json;
+    use files::TempDir;
+    use graph::ProjectGraph;
+    use super::render_metadata;
+
+    #[test]
+    fn render_metadata_keeps_its_keys() {
+        let dir = TempDir::new("metadata");
+        let root = dir.path();
+        File::create(root.join("Clink.toml")).unwrap()
+            .write_all(b"[package]\nname = \"App\"\nversion = \"1.2.0\"\ntype = \"application\"\n").unwrap();
+
+        let graph = ProjectGraph::load(root).unwrap();
+        let metadata = Json::from_str(&render_metadata(&graph)).unwrap();
+
+        // Removing or renaming any of these needs a new format version
+        let metadata = metadata.as_object().unwrap();
+        assert_eq!(metadata["format_version"], Json::U64(1));
+        assert_eq!(metadata.keys().collect::<Vec<_>>(),
+            vec!("format_version", "name", "patched", "path", "projects", "roots", "solution"));
+
+        let project = metadata["projects"][0].as_object().unwrap();
+        assert_eq!(project.keys().collect::<Vec<_>>(), vec!(
+            "compile_files", "defines", "dependencies", "features", "generated", "include_files",
+            "include_paths", "kind", "manifest_path", "name", "path", "platform_defines",
+            "platform_include_paths", "version",
+        ));
+        assert_eq!(project["name"], Json::String("App".into()));
+        assert_eq!(project["version"], Json::String("1.2.0".into()));
+        assert_eq!(project["kind"], Json::String("application".into()));
+        assert_eq!(project["generated"].as_object().unwrap().keys().collect::<Vec<_>>(),
+            vec!("debug", "release", "vcxproj", "vcxproj_filters"));
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::HashSet;
+use std::env::consts::EXE_SUFFIX;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::SystemTime;
+use cfg::Platform;
+use graph::{ProjectGraph, ResolvedProject};
+use native::Profile;
+use native::depfile::parse_depfile;
+use native::toolchain::Toolchain;
+use project::ProjectClass;
+use files;
+use ClinkError;
+
+/// Compiles a project graph directly with gcc or clang, without going through another build
+/// system. Output is placed in target/<profile>/ next to the root project or workspace.
+pub struct NativeBuilder {
+    profile: Profile,
+    jobs: usize,
+}
+
+struct CompileJob {
+    source: PathBuf,
+    object: PathBuf,
+    depfile: PathBuf,
+    command: Vec<String>,
+}
+
+impl NativeBuilder {
+    pub fn new(profile: Profile) -> Self {
+        let jobs = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+        NativeBuilder {
+            profile,
+            jobs,
+        }
+    }
+
+    /// Set the maximum amount of compilers to run at the same time.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = if jobs == 0 { 1 } else { jobs };
+        self
+    }
+
+    /// The directory all output for this builder's profile ends up in.
+    pub fn target_dir(&self, graph: &ProjectGraph) -> PathBuf {
+        let mut path = files::clone_push_path(graph.path(), "target");
+        path.push(self.profile.dir_name());
+        path
+    }
+
+    /// The library or executable a project builds into.
+    pub fn artifact_path(&self, graph: &ProjectGraph, project: &ResolvedProject) -> PathBuf {
+        let filename = match project.class {
+            ProjectClass::Library => format!("lib{}.a", project.name),
+            ProjectClass::Application | ProjectClass::Test => format!("{}{}", project.name, EXE_SUFFIX),
+        };
+        files::clone_push_path(&self.target_dir(graph), &filename)
+    }
+
+    /// Build every project in the graph, as the graph is on the platform clink is running on.
+    pub fn build(&self, graph: &ProjectGraph) -> Result<(), ClinkError> {
+        let graph = &graph.for_platform(&Platform::host());
+        let toolchain = Toolchain::detect(graph.settings());
+        let target_dir = self.target_dir(graph);
+        try!(fs::create_dir_all(&target_dir).map_err(|e|
+            ClinkError::BuildFailed(format!("Could not create {}: {}", target_dir.display(), e))
+        ));
+
+        // Find all objects that are out of date, these don't depend on each other so all projects
+        // can be compiled at the same time
+        let mut jobs = Vec::new();
+        for project in graph.projects() {
+            let mut stale = try!(self.compile_jobs(project, &toolchain, &target_dir));
+            if !stale.is_empty() {
+                println!("{:>12} {} ({})", "Compiling", project.name, project.path.display());
+            }
+            jobs.append(&mut stale);
+        }
+        try!(run_compile_jobs(jobs, self.jobs));
+
+        // Archive and link in graph order, so libraries are always there before they're needed
+        for project in graph.projects() {
+            let objects = object_paths(project, &target_dir);
+            let artifact = self.artifact_path(graph, project);
+
+            match project.class {
+                ProjectClass::Library => {
+                    // Nothing to archive for header-only libraries
+                    if objects.is_empty() {
+                        continue;
+                    }
+
+                    let mut command = vec!(toolchain.ar.clone(), "rcs".into(), path_arg(&artifact));
+                    command.extend(objects.iter().map(|o| path_arg(o)));
+
+                    if try!(is_stale(&artifact, &objects, &command)) {
+                        println!("{:>12} {}", "Archiving", project.name);
+                        // ar only adds to an archive, remove it so removed objects don't stick around
+                        let _ = fs::remove_file(&artifact);
+                        try!(run_command(&command));
+                        try!(write_command_file(&artifact, &command));
+                    }
+                },
+                ProjectClass::Application | ProjectClass::Test => {
+                    let libraries = self.link_libraries(graph, project);
+                    let mut inputs = objects.clone();
+                    inputs.extend(libraries.iter().cloned());
+
+                    let mut command = vec!(toolchain.cxx.clone());
+                    command.extend(inputs.iter().map(|i| path_arg(i)));
+                    command.push("-o".into());
+                    command.push(path_arg(&artifact));
+
+                    if try!(is_stale(&artifact, &inputs, &command)) {
+                        println!("{:>12} {}", "Linking", project.name);
+                        try!(run_command(&command));
+                        try!(write_command_file(&artifact, &command));
+                    }
+                },
+            }
+        }
+
+        println!("{:>12} {} target(s) in {}", "Finished", self.profile.dir_name(), target_dir.display());
+        Ok(())
+    }
+
+    fn compile_jobs(
+        &self, project: &ResolvedProject, toolchain: &Toolchain, target_dir: &Path
+    ) -> Result<Vec<CompileJob>, ClinkError> {
+        let objects = object_paths(project, target_dir);
+
+        let mut jobs = Vec::new();
+        for (source, object) in project.compile_files.iter().zip(objects) {
+            let depfile = object.with_extension("d");
+
+            // C files go to the C compiler, everything else is C++
+            let is_c = source.extension().map(|e| e == "c").unwrap_or(false);
+            let mut command = vec!(if is_c { toolchain.cc.clone() } else { toolchain.cxx.clone() });
+            command.extend(self.profile.flags().iter().map(|f| f.to_string()));
+            for path in &project.include_paths {
+                command.push(format!("-I{}", path.display()));
+            }
+            for define in &project.settings.defines {
+                command.push(format!("-D{}", define));
+            }
+            command.push("-MMD".into());
+            command.push("-MF".into());
+            command.push(path_arg(&depfile));
+            command.push("-c".into());
+            command.push(path_arg(source));
+            command.push("-o".into());
+            command.push(path_arg(&object));
+
+            // Without a depfile we can't know what headers the object depends on
+            let inputs = match read_depfile(&depfile) {
+                Some(inputs) => inputs,
+                None => vec!(source.clone()),
+            };
+            let stale = !depfile.exists() || try!(is_stale(&object, &inputs, &command));
+
+            if stale {
+                jobs.push(CompileJob {
+                    source: source.clone(),
+                    object,
+                    depfile,
+                    command,
+                });
+            }
+        }
+
+        Ok(jobs)
+    }
+
+    /// Find the libraries an application needs to link against, dependents come before their
+    /// dependencies so the linker can resolve symbols in one pass.
+    fn link_libraries(&self, graph: &ProjectGraph, project: &ResolvedProject) -> Vec<PathBuf> {
+        // Find all transitive dependencies
+        let mut needed = HashSet::new();
+        let mut pending: Vec<&String> = project.dependencies.iter().collect();
+        while let Some(name) = pending.pop() {
+            if needed.insert(name.clone()) {
+                if let Some(dep) = graph.find(name) {
+                    pending.extend(dep.dependencies.iter());
+                }
+            }
+        }
+
+        // The graph is in dependencies-first order, so walking it backwards gives us link order
+        graph.projects().iter().rev()
+            .filter(|p| needed.contains(&p.name))
+            .filter(|p| p.class == ProjectClass::Library && !p.compile_files.is_empty())
+            .map(|p| self.artifact_path(graph, p))
+            .collect()
+    }
+}
+
+/// Get the object file path for every compile file in a project, mirroring the project's
+/// directory structure. Sources outside the project's folder go in a folder named after a hash of
+/// the folder they're in, so their objects still end up in the project's object folder.
+fn object_paths(project: &ResolvedProject, target_dir: &Path) -> Vec<PathBuf> {
+    let mut obj_dir = files::clone_push_path(target_dir, "obj");
+    obj_dir.push(&project.name);
+
+    project.compile_files.iter()
+        .map(|source| {
+            let relative = match source.strip_prefix(&project.path) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => {
+                    let folder = source.parent().unwrap_or(source);
+                    let mut relative = PathBuf::from("external");
+                    relative.push(&files::hash_hex(folder.to_string_lossy().as_bytes())[..16]);
+                    relative.push(source.file_name().unwrap_or_default());
+                    relative
+                },
+            };
+            let mut object = obj_dir.join(relative).into_os_string();
+            object.push(".o");
+            PathBuf::from(object)
+        })
+        .collect()
+}
+
+fn run_compile_jobs(jobs: Vec<CompileJob>, workers: usize) -> Result<(), ClinkError> {
+    let queue = Arc::new(Mutex::new(jobs));
+    let failed = Arc::new(AtomicBool::new(false));
+
+    let handles: Vec<_> = (0..workers).map(|_| {
+        let queue = queue.clone();
+        let failed = failed.clone();
+        thread::spawn(move || {
+            // Keep taking jobs until we run out, or until anything failed
+            while !failed.load(Ordering::SeqCst) {
+                let job = match queue.lock().unwrap().pop() {
+                    Some(job) => job,
+                    None => break,
+                };
+
+                if let Err(e) = compile(&job) {
+                    // Let the user know, the compiler's own output will already be on stderr
+                    let _ = writeln!(::std::io::stderr(), "error: {}", e);
+                    failed.store(true, Ordering::SeqCst);
+                }
+            }
+        })
+    }).collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    if failed.load(Ordering::SeqCst) {
+        Err(ClinkError::BuildFailed("Could not compile all source files".into()))
+    } else {
+        Ok(())
+    }
+}
+
+fn compile(job: &CompileJob) -> Result<(), ClinkError> {
+    if let Some(parent) = job.object.parent() {
+        try!(fs::create_dir_all(parent).map_err(|e|
+            ClinkError::BuildFailed(format!("Could not create {}: {}", parent.display(), e))
+        ));
+    }
+
+    // Remove the old depfile first, if compiling fails we don't want to trust it anymore
+    let _ = fs::remove_file(&job.depfile);
+    try!(run_command(&job.command).map_err(|_|
+        ClinkError::BuildFailed(format!("Failed to compile {}", job.source.display()))
+    ));
+    write_command_file(&job.object, &job.command)
+}
+
+fn run_command(command: &[String]) -> Result<(), ClinkError> {
+    let status = try!(Command::new(&command[0]).args(&command[1..]).status().map_err(|e|
+        ClinkError::BuildFailed(format!("Could not run \"{}\": {}", command[0], e))
+    ));
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(ClinkError::BuildFailed(format!("\"{}\" exited with {}", command[0], status)))
+    }
+}
+
+/// Check if an output needs to be rebuilt, because it's missing, one of its inputs changed after
+/// it was built, or it was built with a different command.
+fn is_stale(output: &Path, inputs: &[PathBuf], command: &[String]) -> Result<bool, ClinkError> {
+    let output_time = match modified(output) {
+        Some(time) => time,
+        None => return Ok(true),
+    };
+
+    for input in inputs {
+        match modified(input) {
+            Some(time) if time <= output_time => {},
+            _ => return Ok(true),
+        }
+    }
+
+    let mut previous = String::new();
+    if let Ok(mut file) = File::open(command_file_path(output)) {
+        let _ = file.read_to_string(&mut previous);
+    }
+    Ok(previous != command.join("\n"))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn read_depfile(path: &Path) -> Option<Vec<PathBuf>> {
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .ok()
+        .map(|_| parse_depfile(&contents))
+}
+
+fn command_file_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".cmd");
+    PathBuf::from(path)
+}
+
+fn write_command_file(output: &Path, command: &[String]) -> Result<(), ClinkError> {
+    let path = command_file_path(output);
+    let mut file = try!(File::create(&path).map_err(|e|
+        ClinkError::BuildFailed(format!("Could not write {}: {}", path.display(), e))
+    ));
+    let _ = write!(file, "{}", command.join("\n"));
+    Ok(())
+}
+
+fn path_arg(path: &Path) -> String {
+    format!("{}", path.display())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+    use files::TempDir;
+    use graph::ProjectGraph;
+    use native::Profile;
+    use super::{NativeBuilder, modified};
+
+    fn write_file(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path).unwrap().write_all(contents.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn build_only_recompiles_objects_using_changed_header() {
+        let dir = TempDir::new("builder");
+        let app = dir.path().join("App");
+        write_file(&app.join("Clink.toml"), concat!(
+            "[package]\nname = \"App\"\ntype = \"application\"\n",
+            "[features]\ndefault = [\"shared\"]\nshared = { sources = [\"../Shared\"] }\n",
+        ));
+        write_file(&app.join("include").join("answer.hpp"), "#define ANSWER 42\n");
+        write_file(&app.join("src").join("main.cpp"), concat!(
+            "#include \"answer.hpp\"\n",
+            "int other();\n",
+            "int main() { return ANSWER - 42 + other(); }\n",
+        ));
+        write_file(&app.join("src").join("other.cpp"), "int other() { return 0; }\n");
+        write_file(&dir.path().join("Shared").join("shared.cpp"), "int shared() { return 0; }\n");
+
+        let graph = ProjectGraph::load(&app).unwrap();
+        let builder = NativeBuilder::new(Profile::Debug);
+        builder.build(&graph).unwrap();
+
+        // The source outside the project still gets its object in target/
+        let obj = builder.target_dir(&graph).join("obj").join("App");
+        let main = obj.join("src").join("main.cpp.o");
+        let other = obj.join("src").join("other.cpp.o");
+        let external: Vec<_> = fs::read_dir(obj.join("external")).unwrap().collect();
+        assert_eq!(external.len(), 1);
+        assert!(!dir.path().join("Shared").join("shared.cpp.o").exists());
+        assert!(builder.artifact_path(&graph, graph.find("App").unwrap()).is_file());
+
+        let main_built = modified(&main).unwrap();
+        let other_built = modified(&other).unwrap();
+        File::options().write(true).open(app.join("include").join("answer.hpp")).unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+        builder.build(&graph).unwrap();
+
+        assert!(modified(&main).unwrap() > main_built);
+        assert_eq!(modified(&other).unwrap(), other_built);
+    }
+}
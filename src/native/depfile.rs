@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+/// Parse the prerequisites out of a make-style depfile as written by `gcc -MMD` or `clang -MMD`.
+pub fn parse_depfile(contents: &str) -> Vec<PathBuf> {
+    // Skip over the target, it's separated from the prerequisites by a colon and whitespace
+    let mut chars = contents.chars().peekable();
+    let mut last = ' ';
+    while let Some(c) = chars.next() {
+        if c == ':' && last != '\\' && chars.peek().map(|c| c.is_whitespace()).unwrap_or(true) {
+            break;
+        }
+        last = c;
+    }
+
+    // Split the rest on whitespace, taking into account escaped spaces and line continuations
+    let mut paths = Vec::new();
+    let mut current = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.peek().cloned() {
+                Some(' ') => { current.push(' '); chars.next(); },
+                Some('\n') | Some('\r') => { chars.next(); },
+                _ => current.push(c),
+            },
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    paths.push(PathBuf::from(current.clone()));
+                    current.clear();
+                }
+            },
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        paths.push(PathBuf::from(current));
+    }
+
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use super::parse_depfile;
+
+    #[test]
+    fn parse_depfile_handles_continuations_and_escapes() {
+        let deps = parse_depfile("obj/main.o: src/main.cpp \\\n include/My\\ Lib/lib.hpp\n");
+        assert_eq!(deps, vec!(PathBuf::from("src/main.cpp"), PathBuf::from("include/My Lib/lib.hpp")));
+    }
+}
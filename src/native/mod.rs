@@ -0,0 +1,28 @@
+mod builder;
+mod depfile;
+mod toolchain;
+
+pub use self::builder::NativeBuilder;
+pub use self::toolchain::Toolchain;
+
+/// The set of compiler flags a native build is done with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Profile {
+    Debug, Release
+}
+
+impl Profile {
+    pub fn dir_name(&self) -> &'static str {
+        match *self {
+            Profile::Debug => "debug",
+            Profile::Release => "release",
+        }
+    }
+
+    pub fn flags(&self) -> &'static [&'static str] {
+        match *self {
+            Profile::Debug => &["-g", "-O0"],
+            Profile::Release => &["-O2", "-DNDEBUG"],
+        }
+    }
+}
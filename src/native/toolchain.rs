@@ -0,0 +1,29 @@
+use std::env;
+use settings::BuildSettings;
+
+/// The programs used to compile, archive and link a native build.
+#[derive(Clone, Debug)]
+pub struct Toolchain {
+    pub cc: String,
+    pub cxx: String,
+    pub ar: String,
+}
+
+impl Toolchain {
+    /// Find the toolchain to use, the CC, CXX and AR environment variables take priority over the
+    /// compilers configured in the project's build settings.
+    pub fn detect(settings: &BuildSettings) -> Self {
+        Toolchain {
+            cc: find_program("CC", &settings.cc, "cc"),
+            cxx: find_program("CXX", &settings.cxx, "c++"),
+            ar: find_program("AR", &None, "ar"),
+        }
+    }
+}
+
+fn find_program(variable: &str, configured: &Option<String>, default: &str) -> String {
+    env::var(variable).ok()
+        .filter(|v| !v.is_empty())
+        .or_else(|| configured.clone())
+        .unwrap_or_else(|| default.into())
+}
@@ -1,18 +1,42 @@
+use std::collections::BTreeSet;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::io::Read;
+use semver::Version;
 use toml;
-use visualstudio::{self, ProjFiles, SlnFile, VcxprojFile, ProjDesc, VcxprojType};
+use visualstudio::{self, ProjFiles, VisualStudioGenerator};
 use files;
-use dependency::{Dependency};
-use tomlvalue::{toml_value_table, toml_value_str, toml_table};
+use cfg::Cfg;
+use dependency::{self, Dependency};
+use features::{self, Feature};
+use generator::Generator;
+use graph::ProjectGraph;
+use settings::BuildSettings;
+use target::{self, Target, TargetKind};
+use tomlvalue::{parse_toml, toml_value_table, toml_value_str, toml_table};
 use ClinkError;
 
 pub struct Project {
     path: PathBuf,
     name: String,
+    version: Option<Version>,
     class: ProjectClass,
     dependencies: Vec<Dependency>,
+    /// Dependencies only used by the project's tests and examples.
+    dev_dependencies: Vec<Dependency>,
+    features: Vec<Feature>,
+    /// Replacements for dependencies anywhere in the graph, used when this is the root project.
+    patches: Vec<Dependency>,
+    settings: BuildSettings,
+    /// Build settings that only apply on platforms matching a condition.
+    platform_settings: Vec<(Cfg, BuildSettings)>,
+    /// The folder the project's own sources are in, if not the whole project folder.
+    source_path: Option<PathBuf>,
+    targets: Vec<Target>,
+    /// The project's files when given in code, instead of scanning the project folder for them.
+    files: Option<ProjFiles>,
+    /// Hash of the project's Clink.toml, to detect changes to it.
+    checksum: String,
 }
 
 impl Project {
@@ -20,30 +44,60 @@ impl Project {
         Project {
             path: "".into(),
             name: name,
+            version: None,
             class: ProjectClass::Library,
             dependencies: Vec::new(),
+            dev_dependencies: Vec::new(),
+            features: Vec::new(),
+            patches: Vec::new(),
+            settings: BuildSettings::new(),
+            platform_settings: Vec::new(),
+            source_path: None,
+            targets: Vec::new(),
+            files: None,
+            checksum: String::new(),
         }
     }
 
+    /// Start assembling a project in code, instead of reading it from a Clink.toml.
+    pub fn builder<P: Into<PathBuf>>(name: String, path: P) -> ProjectBuilder {
+        let mut project = Project::new(name);
+        project.path = path.into();
+        ProjectBuilder { project }
+    }
+
     pub fn open<P: Into<PathBuf>>(path: P) -> Result<Self, ClinkError> {
-        // Find the project description file
+        // Find and parse the project description file
         let path: PathBuf = path.into();
-        let toml_path = files::clone_push_path(&path, "Clink.toml");
-
-        // Read all the text from it
-        let mut f = try!(File::open(toml_path).map_err(|_|
-            ClinkError::InvalidProjectStructure(path.clone(), "Could not find Clink.toml".into())
-        ));
-        let mut toml_str = String::new();
-        f.read_to_string(&mut toml_str).unwrap();
-
-        // Parse in the toml
-        let toml = toml::Parser::new(&toml_str).parse().unwrap();
+        let toml_str = try!(read_manifest_text(&path));
+        let toml = try!(parse_toml(&files::clone_push_path(&path, "Clink.toml"), &toml_str));
 
         // Read in generic information
         let package = try!(toml_value_table(&toml, "package"));
         let name: String = try!(toml_value_str(&package, "name")).into();
-        let class: String = try!(toml_value_str(&package, "type")).into();
+        let version = if package.contains_key("version") {
+            Some(try!(parse_version(try!(toml_value_str(package, "version")))))
+        } else {
+            None
+        };
+
+        // A [lib] table makes the project a library, and can move its sources
+        let mut source_path = None;
+        let class = if let Some(lib) = toml.get("lib") {
+            let lib = try!(toml_table(lib, "lib"));
+            if lib.contains_key("path") {
+                source_path = Some(files::clone_push_path(&path, try!(toml_value_str(lib, "path"))));
+            }
+
+            if package.contains_key("type") && try!(toml_value_str(package, "type")) != "library" {
+                return Err(ClinkError::InvalidProjectFile(
+                    "A project with a [lib] table must be of type \"library\"".into()
+                ));
+            }
+            ProjectClass::Library
+        } else {
+            try!(ProjectClass::parse(try!(toml_value_str(package, "type"))))
+        };
 
         // Read in all dependencies
         let mut dependencies = Vec::new();
@@ -51,162 +105,347 @@ impl Project {
             let deps_table = try!(toml_table(deps_table, "dependencies"));
 
             for (key, value) in deps_table {
-                let dep_path = try!(value.as_str()
-                    .ok_or_else(||
-                        ClinkError::InvalidProjectFile(format!("{} is invalid type (expected string)", key))
-                    )
-                );
-                dependencies.push(Dependency::at(&path, key.clone(), &dep_path));
+                dependencies.push(try!(Dependency::parse(&path, key.clone(), value)));
+            }
+        }
+
+        // Read in the dependencies of the tests and examples
+        let mut dev_dependencies = Vec::new();
+        if let Some(deps_table) = toml.get("dev-dependencies") {
+            let deps_table = try!(toml_table(deps_table, "dev-dependencies"));
+
+            for (key, value) in deps_table {
+                dev_dependencies.push(try!(Dependency::parse(&path, key.clone(), value)));
             }
         }
 
+        // Read in the dependencies and settings that only apply to some platforms
+        let mut platform_settings = Vec::new();
+        if let Some(targets_table) = toml.get("target") {
+            let targets_table = try!(toml_table(targets_table, "target"));
+
+            for (key, value) in targets_table {
+                let cfg = try!(Cfg::parse(key));
+                let target_table = try!(toml_table(value, key));
+
+                if let Some(deps_table) = target_table.get("dependencies") {
+                    let deps_table = try!(toml_table(deps_table, "dependencies"));
+                    for (key, value) in deps_table {
+                        let dep = try!(Dependency::parse(&path, key.clone(), value));
+                        dependencies.push(dep.with_platform(cfg.clone()));
+                    }
+                }
+                if let Some(deps_table) = target_table.get("dev-dependencies") {
+                    let deps_table = try!(toml_table(deps_table, "dev-dependencies"));
+                    for (key, value) in deps_table {
+                        let dep = try!(Dependency::parse(&path, key.clone(), value));
+                        dev_dependencies.push(dep.with_platform(cfg.clone()));
+                    }
+                }
+                if target_table.contains_key("build") {
+                    platform_settings.push((cfg, try!(BuildSettings::parse(target_table))));
+                }
+            }
+        }
+
+        // Read in the features that can be enabled on the project
+        let features = try!(features::parse_features(&toml, &path, &dependencies));
+
+        // Read in the dependency replacements
+        let patches = try!(dependency::parse_patches(&toml, &path));
+
+        // Read in the build settings
+        let settings = try!(BuildSettings::parse(&toml));
+
+        // Read in the binaries, examples and tests, these are separate projects in the same folder
+        let mut targets = Vec::new();
+        for kind in &TargetKind::all() {
+            targets.extend(try!(target::parse_targets(&toml, *kind, &path, &name)));
+        }
+
         // Store all the information into a helper struct
         Ok(Project {
-            path: path,
-            name: name,
-            class: try!(ProjectClass::parse(&class)),
-            dependencies: dependencies,
+            path,
+            name,
+            version,
+            class,
+            dependencies,
+            dev_dependencies,
+            features,
+            patches,
+            settings,
+            platform_settings,
+            source_path,
+            targets,
+            files: None,
+            checksum: files::hash_hex(toml_str.as_bytes()),
         })
     }
 
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
     pub fn name(&self) -> &String {
         &self.name
     }
 
+    /// The version of the project, if it has one.
+    pub fn version(&self) -> Option<&Version> {
+        self.version.as_ref()
+    }
+
     pub fn class(&self) -> &ProjectClass {
         &self.class
     }
 
+    pub fn dependencies(&self) -> &Vec<Dependency> {
+        &self.dependencies
+    }
+
+    /// The dependencies of the project's tests and examples, these are not passed on to projects
+    /// depending on this project.
+    pub fn dev_dependencies(&self) -> &Vec<Dependency> {
+        &self.dev_dependencies
+    }
+
+    pub fn features(&self) -> &Vec<Feature> {
+        &self.features
+    }
+
+    /// Find all features enabled by a set of requested features.
+    pub fn enabled_features(&self, requested: &BTreeSet<String>) -> Result<BTreeSet<String>, ClinkError> {
+        features::expand_features(&self.name, &self.features, requested)
+    }
+
+    /// Find the dependencies used with a set of enabled features, and the features requested
+    /// from each of them.
+    pub fn enabled_dependencies(&self, features: &BTreeSet<String>) -> Vec<(&Dependency, BTreeSet<String>)> {
+        let enables: Vec<&String> = self.features.iter()
+            .filter(|f| features.contains(&f.name))
+            .flat_map(|f| f.enables.iter())
+            .collect();
+
+        // Like in cargo, enabling a feature of an optional dependency enables the dependency too
+        self.dependencies.iter()
+            .filter(|d| !d.is_optional() || enables.iter().any(|e| e.split('/').next() == Some(d.name().as_str())))
+            .map(|dep| (dep, requested_features(dep, &enables)))
+            .collect()
+    }
+
+    /// The dev-dependencies of the project, and the features requested from each of them.
+    pub fn enabled_dev_dependencies(&self) -> Vec<(&Dependency, BTreeSet<String>)> {
+        self.dev_dependencies.iter()
+            .map(|dep| (dep, requested_features(dep, &[])))
+            .collect()
+    }
+
+    /// The dependencies from the [patch] table, these replace dependencies on packages with the
+    /// same name when this project is the root of a graph.
+    pub fn patches(&self) -> &Vec<Dependency> {
+        &self.patches
+    }
+
+    pub fn settings(&self) -> &BuildSettings {
+        &self.settings
+    }
+
+    /// Build settings that only apply on platforms matching a condition, on top of `settings`.
+    pub fn platform_settings(&self) -> &Vec<(Cfg, BuildSettings)> {
+        &self.platform_settings
+    }
+
+    /// A hash of the project's Clink.toml.
+    pub fn checksum(&self) -> &String {
+        &self.checksum
+    }
+
+    /// The binaries, examples and tests of this project, which are built as separate projects.
+    pub fn targets(&self) -> &Vec<Target> {
+        &self.targets
+    }
+
+    /// Find the project's own .hpp and .cpp files, leaving out those that belong to its targets
+    /// or are only used with a feature.
+    pub fn scan_files(&self) -> Result<ProjFiles, ClinkError> {
+        if let Some(ref files) = self.files {
+            return Ok(files.clone());
+        }
+
+        let mut excluded: Vec<PathBuf> = TargetKind::all().iter()
+            .map(|k| files::clone_push_path(&self.path, k.folder()))
+            .collect();
+        excluded.extend(self.targets.iter().map(|t| t.path.clone()));
+        excluded.extend(self.features.iter().flat_map(|f| f.sources.iter().cloned()));
+
+        match self.source_path {
+            Some(ref source_path) => {
+                // The include folder always belongs to the project
+                let mut files = try!(ProjFiles::scan(source_path, &excluded));
+                let headers = try!(ProjFiles::scan(&files::clone_push_path(&self.path, "include"), &excluded));
+                files.include.extend(headers.include);
+                Ok(files)
+            },
+            None => ProjFiles::scan(&self.path, &excluded),
+        }
+    }
+
     pub fn to_toml(&self) -> toml::Value {
         let mut table = toml::Table::new();
 
         // Write out the project's base information
         let mut package = toml::Table::new();
         package.insert("name".into(), toml::Value::String(self.name.clone()));
+        if let Some(ref version) = self.version {
+            package.insert("version".into(), toml::Value::String(version.to_string()));
+        }
         package.insert("type".into(), toml::Value::String(self.class.to_string()));
         table.insert("package".into(), toml::Value::Table(package));
 
         toml::Value::Table(table)
     }
 
-    /// Generate the visual studio solution file for this project.
+    /// Generate the visual studio solution file for this project, updating the Clink.lock next
+    /// to it.
     pub fn generate_sln(&self) -> Result<(), ClinkError> {
-        // Go over this project and all dependencies and generate vcxprojs for them
-        let mut projects = Vec::new();
-        try!(self.generate_vcxproj_recursive(&mut projects));
-
-        // Write out the sln
-        let mut sln = SlnFile::new();
-        for proj in projects {
-            sln.add_project(proj);
-        }
-        let filename = format!("{}.sln", self.name);
-        sln.write_to(files::clone_push_path(&self.path, &filename));
+        let graph = try!(ProjectGraph::resolve(self));
+        try!(graph.update_lockfile(files::clone_push_path(&self.path, "Clink.lock"), false));
+        VisualStudioGenerator::new().generate(&graph)
+    }
 
-        Ok(())
+    /// Generate the solution like `generate_sln`, requiring Clink.lock to be up to date.
+    pub fn generate_sln_locked(&self) -> Result<(), ClinkError> {
+        let graph = try!(ProjectGraph::resolve(self));
+        try!(graph.update_lockfile(files::clone_push_path(&self.path, "Clink.lock"), true));
+        VisualStudioGenerator::new().generate(&graph)
     }
 
-    fn generate_vcxproj_recursive(&self, projects: &mut Vec<ProjDesc>) -> Result<(), ClinkError> {
-        // Go over all dependencies
-        for dep in &self.dependencies {
-            // TODO: Implement external dependencies
-            if dep.is_external() {
-                continue;
-            }
+    pub fn generate_vcxproj_filters(&self) -> Result<(), ClinkError> {
+        let files = try!(self.scan_files());
+        let filename = format!("{}.vcxproj.filters", self.name);
+        visualstudio::generate_filters(&self.path, &files, files::clone_push_path(&self.path, &filename))
+    }
+}
 
-            // Check if this dependency has already been generated
-            if let Some(found) = projects.iter().find(|p| &p.name == dep.name()) {
-                // It exists already, make sure the path is the same then skip it
-                let mut found_path = found.vcxproj_path.clone();
-                found_path.pop();
-                if dep.path() != &found_path {
-                    // TODO: Improve error handling, add a DependencyConflict error
-                    panic!("Multiple dependencies with same name and different path");
-                }
+/// Assembles a project in code, for tools that embed clink without writing a Clink.toml.
+pub struct ProjectBuilder {
+    project: Project,
+}
 
-                continue;
-            }
+impl ProjectBuilder {
+    pub fn version(mut self, version: Version) -> Self {
+        self.project.version = Some(version);
+        self
+    }
 
-            // Open the project and generate it as well
-            let proj = try!(dep.open());
-            try!(proj.generate_vcxproj_recursive(projects));
-        }
+    pub fn class(mut self, class: ProjectClass) -> Self {
+        self.project.class = class;
+        self
+    }
 
-        // Generate a vcxproj for this project
-        let desc = self.generate_vcxproj(projects);
+    pub fn dependency(mut self, dependency: Dependency) -> Self {
+        self.project.dependencies.push(dependency);
+        self
+    }
 
-        // Track the generated project
-        projects.push(desc);
+    pub fn dev_dependency(mut self, dependency: Dependency) -> Self {
+        self.project.dev_dependencies.push(dependency);
+        self
+    }
 
-        Ok(())
+    pub fn feature(mut self, feature: Feature) -> Self {
+        self.project.features.push(feature);
+        self
     }
 
-    /// Generate the visual studio project file and filters file for this project and return a
-    /// descriptor for it.
-    // TODO: Change Vec<ProjDesc> to Vec<AvailableDependency> so we can track more data
-    pub fn generate_vcxproj(&self, available_dependencies: &Vec<ProjDesc>) -> ProjDesc {
-        // Get the project type for our clink project type string
-        let class = match &self.class {
-            &ProjectClass::Application => VcxprojType::Application,
-            &ProjectClass::Library => VcxprojType::StaticLibrary,
-        };
+    pub fn define(mut self, define: String) -> Self {
+        self.project.settings.defines.push(define);
+        self
+    }
 
-        // Create the project file representation
-        let mut vcxproj = VcxprojFile::new(self.name.clone(), class);
+    pub fn settings(mut self, settings: BuildSettings) -> Self {
+        self.project.settings = settings;
+        self
+    }
 
-        // Add the include folder to the include path
-        vcxproj.add_include_path(files::clone_push_path(&self.path, "include"));
+    pub fn platform_settings(mut self, cfg: Cfg, settings: BuildSettings) -> Self {
+        self.project.platform_settings.push((cfg, settings));
+        self
+    }
 
-        // Find the .hpp and .cpp files the vcxproj needs
-        let files = ProjFiles::scan(&self.path);
-        for file in &files.compile {
-            vcxproj.add_compile(file.into());
-        }
-        for file in &files.include {
-            vcxproj.add_include(file.into());
-        }
+    pub fn target(mut self, target: Target) -> Self {
+        self.project.targets.push(target);
+        self
+    }
 
-        // Look up and add all the dependencies
-        for dep in &self.dependencies {
-            // TODO: Implement external dependencies
-            if dep.is_external() {
-                continue;
-            }
+    /// Add a source file, once a project has files added this way its folder isn't scanned.
+    pub fn compile_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.project.files.get_or_insert_with(ProjFiles::default).compile.push(path.into());
+        self
+    }
 
-            // Find and add a reference for this dependency
-            let desc = available_dependencies.iter().find(|p| &p.name == dep.name())
-                .expect("Internal error, dependency not found!");
-            vcxproj.add_reference(desc.clone());
+    /// Add a header file, once a project has files added this way its folder isn't scanned.
+    pub fn include_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.project.files.get_or_insert_with(ProjFiles::default).include.push(path.into());
+        self
+    }
 
-            // Add the dependency to the include path
-            vcxproj.add_include_path(files::clone_push_path(&dep.path(), "include"));
-        }
+    pub fn build(self) -> Project {
+        let mut project = self.project;
+        project.checksum = files::hash_hex(project.to_toml().to_string().as_bytes());
+        project
+    }
+}
 
-        // TODO: Check if the vcxproj needs to be re-generated or not before actually doing it
-        // We should only re-generate if the files don't match
-        // TODO: Re-use the same project GUID
-        // Both of these could make use of a Clink.cache file
+/// The features a project requests from a dependency, given what its enabled features enable.
+fn requested_features(dep: &Dependency, enables: &[&String]) -> BTreeSet<String> {
+    let mut requested: BTreeSet<String> = dep.features().iter().cloned().collect();
+    if dep.default_features() {
+        requested.insert("default".into());
+    }
 
-        // Write the vcxproj and vcxproj.filters to disk
-        let filename = format!("{}.vcxproj", self.name);
-        let desc = vcxproj.write_to(files::clone_push_path(&self.path, &filename));
-        let filename = format!("{}.vcxproj.filters", self.name);
-        visualstudio::generate_filters(&self.path, &files, files::clone_push_path(&self.path, &filename));
+    let prefix = format!("{}/", dep.name());
+    requested.extend(enables.iter()
+        .filter(|e| e.starts_with(&prefix))
+        .map(|e| e[prefix.len()..].to_string())
+    );
+    requested
+}
 
-        desc
-    }
+fn parse_version(value: &str) -> Result<Version, ClinkError> {
+    Version::parse(value).map_err(|e|
+        ClinkError::InvalidProjectFile(format!("\"{}\" is not a valid version: {}", value, e))
+    )
+}
 
-    pub fn generate_vcxproj_filters(&self) {
-        let files = ProjFiles::scan(&self.path);
-        let filename = format!("{}.vcxproj.filters", self.name);
-        visualstudio::generate_filters(&self.path, &files, files::clone_push_path(&self.path, &filename));
-    }
+/// Read and parse the Clink.toml in a folder.
+pub fn read_manifest(path: &Path) -> Result<toml::Table, ClinkError> {
+    let toml_str = try!(read_manifest_text(path));
+
+    // Parse in the toml
+    parse_toml(&files::clone_push_path(path, "Clink.toml"), &toml_str)
+}
+
+/// Read the text of the Clink.toml in a folder.
+pub fn read_manifest_text(path: &Path) -> Result<String, ClinkError> {
+    let toml_path = files::clone_push_path(path, "Clink.toml");
+
+    // Read all the text from it
+    let mut f = try!(File::open(&toml_path).map_err(|_|
+        ClinkError::InvalidProjectStructure(path.to_path_buf(), "Could not find Clink.toml".into())
+    ));
+    let mut toml_str = String::new();
+    try!(f.read_to_string(&mut toml_str).map_err(|e| ClinkError::Io(toml_path, e)));
+
+    Ok(toml_str)
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum ProjectClass {
     Application,
-    Library
+    Library,
+    /// An application that is run by `clink test`.
+    Test,
 }
 
 impl ProjectClass {
@@ -214,20 +453,35 @@ impl ProjectClass {
         match value {
             "application" => Ok(ProjectClass::Application),
             "library" => Ok(ProjectClass::Library),
+            "test" => Ok(ProjectClass::Test),
             v => Err(ClinkError::InvalidProjectFile(format!("\"{}\" is not a valid project type", v)))
         }
     }
 
+    /// Check if projects of this class build into something that can be run.
+    pub fn is_executable(&self) -> bool {
+        *self != ProjectClass::Library
+    }
+
     pub fn to_string(&self) -> String {
         match self {
             &ProjectClass::Application => "application".into(),
             &ProjectClass::Library => "library".into(),
+            &ProjectClass::Test => "test".into(),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::collections::BTreeSet;
+    use dependency::Dependency;
+    use features::Feature;
+    use std::path::Path;
+    use files::TempDir;
+    use target::TargetKind;
     use super::{Project, ProjectClass};
 
     #[test]
@@ -236,4 +490,110 @@ mod tests {
         assert_eq!(proj.name(), "MyProject");
         assert_eq!(proj.class(), &ProjectClass::Library);
     }
+
+    #[test]
+    fn builder_uses_given_files() {
+        let proj = Project::builder("Game".into(), "/nonexistent/Game")
+            .class(ProjectClass::Application)
+            .define("GAME_DEBUG".into())
+            .compile_file("/nonexistent/Game/src/main.cpp")
+            .build();
+        assert_eq!(proj.class(), &ProjectClass::Application);
+        assert_eq!(proj.settings().defines, vec!("GAME_DEBUG"));
+        let files = proj.scan_files().ok().unwrap();
+        assert_eq!(files.compile.len(), 1);
+        assert!(files.include.is_empty());
+    }
+
+    #[test]
+    fn scan_files_leaves_out_tests() {
+        let dir = TempDir::new("project-tests");
+        let root = dir.path();
+        File::create(root.join("Clink.toml")).unwrap()
+            .write_all(b"[package]\nname = \"Math\"\ntype = \"library\"\n").unwrap();
+        for path in &["src/math.cpp", "tests/vector.cpp", "tests/matrix/main.cpp"] {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            File::create(path).unwrap();
+        }
+
+        let project = Project::open(root).unwrap();
+        let files = project.scan_files().unwrap();
+        assert_eq!(files.compile, vec!(root.join("src").join("math.cpp")));
+        let names: Vec<&str> = project.targets().iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!("Math-matrix", "Math-vector"));
+    }
+
+    #[test]
+    fn open_reads_lib_binaries_and_examples() {
+        let dir = TempDir::new("project-targets");
+        let root = dir.path();
+        File::create(root.join("Clink.toml")).unwrap().write_all(concat!(
+            "[package]\nname = \"Engine\"\n[lib]\npath = \"engine\"\n",
+            "[[bin]]\nname = \"packer\"\npath = \"tools/packer\"\n",
+            "[[example]]\nname = \"triangle\"\n",
+        ).as_bytes()).unwrap();
+        for path in &[
+            "engine/engine.cpp", "include/engine.hpp", "tools/packer/main.cpp", "examples/triangle.cpp",
+            "examples/unused.cpp", "src/bin/viewer.cpp", "src/bin/viewer/extra.cpp",
+        ] {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            File::create(path).unwrap();
+        }
+
+        let project = Project::open(root).unwrap();
+        assert_eq!(project.class(), &ProjectClass::Library);
+        let files = project.scan_files().unwrap();
+        assert_eq!(files.compile, vec!(root.join("engine").join("engine.cpp")));
+        assert_eq!(files.include, vec!(root.join("include").join("engine.hpp")));
+
+        // Declaring a [[bin]] turns discovery off, so src/bin/viewer is neither a target nor a source
+        let targets: Vec<(&str, TargetKind, &Path)> = project.targets().iter()
+            .map(|t| (t.name.as_str(), t.kind, t.path.as_path()))
+            .collect();
+        assert_eq!(targets, vec!(
+            ("packer", TargetKind::Bin, root.join("tools").join("packer").as_path()),
+            ("triangle", TargetKind::Example, root.join("examples").join("triangle.cpp").as_path()),
+        ));
+    }
+
+    #[test]
+    fn generate_sln_writes_lockfile() {
+        let dir = TempDir::new("project");
+        let root = dir.path();
+        for &(name, manifest) in &[
+            ("Math", "[package]\nname = \"Math\"\ntype = \"library\"\n"),
+            ("Game", "[package]\nname = \"Game\"\ntype = \"application\"\n[dependencies]\nMath = \"../Math\"\n"),
+        ] {
+            fs::create_dir_all(root.join(name)).unwrap();
+            File::create(root.join(name).join("Clink.toml")).unwrap().write_all(manifest.as_bytes()).unwrap();
+        }
+
+        let game = Project::open(root.join("Game")).unwrap();
+        assert!(game.generate_sln_locked().is_err());
+        game.generate_sln().unwrap();
+        assert!(root.join("Game").join("Clink.lock").is_file());
+        assert!(game.generate_sln_locked().is_ok());
+    }
+
+    #[test]
+    fn dependency_feature_enables_optional_dependency() {
+        let proj = Project::builder("Engine".into(), "/nonexistent/Engine")
+            .dependency(Dependency::at("/nonexistent/Engine", "Fmt".into(), "../Fmt").with_optional(true))
+            .feature(Feature {
+                name: "log".into(),
+                enables: vec!("Fmt/color".into()),
+                defines: Vec::new(),
+                sources: Vec::new(),
+            })
+            .build();
+
+        assert!(proj.enabled_dependencies(&BTreeSet::new()).is_empty());
+        let enabled: BTreeSet<String> = vec!("log".to_string()).into_iter().collect();
+        let deps = proj.enabled_dependencies(&enabled);
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].0.name(), "Fmt");
+        assert!(deps[0].1.contains("color"));
+    }
 }
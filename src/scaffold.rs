@@ -0,0 +1,236 @@
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use walkdir::{WalkDir, WalkDirIterator};
+use files;
+use git;
+use project::{Project, ProjectClass};
+use ClinkError;
+
+/// The files clink generates, which don't belong in version control.
+const GITIGNORE: &str = "\
+# Generated by clink
+*.sln
+*.vcxproj
+*.vcxproj.filters
+*.vcxproj.user
+.vs/
+/target/
+";
+
+/// What to create a new project with.
+pub struct NewProject {
+    pub name: String,
+    pub class: ProjectClass,
+    /// A folder to copy the project's files from, instead of the built-in starter files.
+    pub template: Option<PathBuf>,
+    /// Overwrite an existing Clink.toml.
+    pub force: bool,
+}
+
+impl NewProject {
+    pub fn new(name: String, class: ProjectClass) -> Self {
+        NewProject {
+            name,
+            class,
+            template: None,
+            force: false,
+        }
+    }
+}
+
+/// Find a template by name in CLINK_HOME/templates, or use it as a path if it isn't there.
+pub fn find_template(name: &str) -> Result<PathBuf, ClinkError> {
+    let installed = files::clone_push_path(&files::clone_push_path(&git::clink_home(), "templates"), name);
+    let path = if installed.is_dir() { installed } else { PathBuf::from(name) };
+
+    if !path.is_dir() {
+        return Err(ClinkError::InvalidProjectStructure(path, "Could not find template folder".into()));
+    }
+    Ok(path)
+}
+
+/// Create a project in an existing folder, returning the files that were created. Files that are
+/// already there are left alone, except for Clink.toml if forced.
+pub fn init_project(path: &Path, new: &NewProject) -> Result<Vec<PathBuf>, ClinkError> {
+    let manifest = files::clone_push_path(path, "Clink.toml");
+    if manifest.exists() && !new.force {
+        return Err(ClinkError::InvalidProjectStructure(
+            manifest, "A Clink.toml already exists, use --force to overwrite it".into()
+        ));
+    }
+
+    let mut created = Vec::new();
+    match new.template {
+        Some(ref template) => try!(copy_template(template, path, new, &mut created)),
+        None => try!(write_starter_files(path, new, &mut created)),
+    }
+
+    // Templates don't need to come with a .gitignore or manifest of their own
+    let gitignore = files::clone_push_path(path, ".gitignore");
+    if !gitignore.exists() {
+        try!(write_file(&gitignore, GITIGNORE));
+        created.push(gitignore);
+    }
+    if !created.contains(&manifest) {
+        let project = Project::builder(new.name.clone(), path.to_path_buf()).class(new.class.clone()).build();
+        try!(write_file(&manifest, &project.to_toml().to_string()));
+        created.push(manifest);
+    }
+
+    Ok(created)
+}
+
+/// Create a project in a new folder, returning the files that were created.
+pub fn new_project(path: &Path, new: &NewProject) -> Result<Vec<PathBuf>, ClinkError> {
+    if path.exists() {
+        return Err(ClinkError::InvalidProjectStructure(path.to_path_buf(), "The folder already exists".into()));
+    }
+    try!(fs::create_dir_all(path).map_err(|e| ClinkError::WriteFailed(path.to_path_buf(), e)));
+
+    init_project(path, new)
+}
+
+fn write_starter_files(path: &Path, new: &NewProject, created: &mut Vec<PathBuf>) -> Result<(), ClinkError> {
+    let include = files::clone_push_path(&files::clone_push_path(path, "include"), &new.name);
+    try!(fs::create_dir_all(&include).map_err(|e| ClinkError::WriteFailed(include.clone(), e)));
+    let src = files::clone_push_path(path, "src");
+    try!(fs::create_dir_all(&src).map_err(|e| ClinkError::WriteFailed(src.clone(), e)));
+
+    let mut starters = Vec::new();
+    if new.class.is_executable() {
+        starters.push((files::clone_push_path(&src, "main.cpp"), format!(
+            "#include <iostream>\n\nint main()\n{{\n    std::cout << \"Hello from {}!\" << std::endl;\n    return 0;\n}}\n",
+            new.name
+        )));
+    } else {
+        starters.push((files::clone_push_path(&include, &format!("{}.hpp", new.name)), format!(
+            "#pragma once\n\nnamespace {} {{\n    int answer();\n}}\n", identifier(&new.name)
+        )));
+        starters.push((files::clone_push_path(&src, &format!("{}.cpp", new.name)), format!(
+            "#include <{0}/{0}.hpp>\n\nnamespace {1} {{\n    int answer()\n    {{\n        return 42;\n    }}\n}}\n",
+            new.name, identifier(&new.name)
+        )));
+    }
+
+    for (file, contents) in starters {
+        if !file.exists() {
+            try!(write_file(&file, &contents));
+            created.push(file);
+        }
+    }
+
+    Ok(())
+}
+
+/// Copy a template folder into a project, replacing {{name}} and {{type}} in file names and text.
+fn copy_template(template: &Path, path: &Path, new: &NewProject, created: &mut Vec<PathBuf>) -> Result<(), ClinkError> {
+    let manifest = files::clone_push_path(path, "Clink.toml");
+
+    for entry in WalkDir::new(template).min_depth(1).into_iter().filter_entry(|e| e.file_name() != ".git") {
+        let entry = try!(entry.map_err(|e| {
+            let path = e.path().unwrap_or(template).to_path_buf();
+            ClinkError::Io(path, e.into())
+        }));
+        let relative = files::relative_path(template, entry.path());
+        let target = path.join(substitute(&relative.to_string_lossy(), new));
+
+        if entry.file_type().is_dir() {
+            try!(fs::create_dir_all(&target).map_err(|e| ClinkError::WriteFailed(target.clone(), e)));
+            continue;
+        }
+        if target.exists() && target != manifest {
+            continue;
+        }
+
+        let mut contents = Vec::new();
+        try!(File::open(entry.path())
+            .and_then(|mut f| f.read_to_end(&mut contents))
+            .map_err(|e| ClinkError::Io(entry.path().to_path_buf(), e)));
+
+        // Only text files get their placeholders replaced
+        let contents = match String::from_utf8(contents) {
+            Ok(text) => substitute(&text, new).into_bytes(),
+            Err(e) => e.into_bytes(),
+        };
+        try!(File::create(&target)
+            .and_then(|mut f| f.write_all(&contents))
+            .map_err(|e| ClinkError::WriteFailed(target.clone(), e)));
+        created.push(target);
+    }
+
+    Ok(())
+}
+
+fn substitute(text: &str, new: &NewProject) -> String {
+    text.replace("{{name}}", &new.name).replace("{{type}}", &new.class.to_string())
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), ClinkError> {
+    File::create(path)
+        .and_then(|mut f| f.write_all(contents.as_bytes()))
+        .map_err(|e| ClinkError::WriteFailed(path.to_path_buf(), e))
+}
+
+/// Turn a project name into something that can be used as a C++ namespace.
+fn identifier(name: &str) -> String {
+    let mut identifier: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    identifier
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::{Read, Write};
+    use files::TempDir;
+    use project::{Project, ProjectClass};
+    use super::{NewProject, init_project, new_project};
+
+    #[test]
+    fn init_project_refuses_to_overwrite_manifest() {
+        let dir = TempDir::new("scaffold");
+        let path = dir.path().join("my-lib");
+
+        let created = new_project(&path, &NewProject::new("my-lib".into(), ProjectClass::Library)).unwrap();
+        assert!(created.contains(&path.join("include").join("my-lib").join("my-lib.hpp")));
+        assert!(created.contains(&path.join("src").join("my-lib.cpp")));
+        assert!(created.contains(&path.join(".gitignore")));
+
+        let mut again = NewProject::new("my-lib".into(), ProjectClass::Application);
+        assert!(init_project(&path, &again).is_err());
+
+        // Forcing only replaces the manifest, the starter files are kept
+        again.force = true;
+        let created = init_project(&path, &again).unwrap();
+        assert_eq!(created, vec!(path.join("src").join("main.cpp"), path.join("Clink.toml")));
+        assert_eq!(*Project::open(&path).unwrap().class(), ProjectClass::Application);
+    }
+
+    #[test]
+    fn init_project_from_template_adds_missing_gitignore() {
+        let dir = TempDir::new("scaffold-template");
+        let root = dir.path();
+        let template = root.join("template");
+        fs::create_dir_all(template.join("src")).unwrap();
+        File::create(template.join("src").join("{{name}}.cpp")).unwrap().write_all(b"// {{name}}\n").unwrap();
+
+        let mut new = NewProject::new("Game".into(), ProjectClass::Application);
+        new.template = Some(template.clone());
+        let created = new_project(&root.join("Game"), &new).unwrap();
+        assert!(created.contains(&root.join("Game").join("src").join("Game.cpp")));
+        assert!(created.contains(&root.join("Game").join(".gitignore")));
+        assert!(created.contains(&root.join("Game").join("Clink.toml")));
+
+        // A template's own .gitignore is kept
+        File::create(template.join(".gitignore")).unwrap().write_all(b"/build/\n").unwrap();
+        new_project(&root.join("Other"), &new).unwrap();
+        let mut gitignore = String::new();
+        File::open(root.join("Other").join(".gitignore")).unwrap().read_to_string(&mut gitignore).unwrap();
+        assert_eq!(gitignore, "/build/\n");
+    }
+}
@@ -0,0 +1,54 @@
+use toml::Table;
+use tomlvalue::{toml_table, toml_value_str, toml_value_str_array};
+use ClinkError;
+
+/// Build settings that apply to all files in a project, independent of the backend that ends up
+/// consuming them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BuildSettings {
+    pub defines: Vec<String>,
+    /// The C compiler for native builds, only used from the root project.
+    pub cc: Option<String>,
+    /// The C++ compiler for native builds, only used from the root project.
+    pub cxx: Option<String>,
+}
+
+impl BuildSettings {
+    pub fn new() -> Self {
+        BuildSettings::default()
+    }
+
+    /// Read the settings from a project file's [build] table, if it has one.
+    pub fn parse(toml: &Table) -> Result<Self, ClinkError> {
+        let mut settings = BuildSettings::new();
+
+        if let Some(build) = toml.get("build") {
+            let build = try!(toml_table(build, "build"));
+
+            if build.contains_key("defines") {
+                settings.defines = try!(toml_value_str_array(build, "defines"));
+            }
+            if build.contains_key("cc") {
+                settings.cc = Some(try!(toml_value_str(build, "cc")).into());
+            }
+            if build.contains_key("cxx") {
+                settings.cxx = Some(try!(toml_value_str(build, "cxx")).into());
+            }
+        }
+
+        Ok(settings)
+    }
+
+    /// Combine these settings with settings inherited from a parent, such as a workspace. Lists
+    /// are merged with the parent's entries first, our own values override the parent's.
+    pub fn inherit(&self, parent: &BuildSettings) -> BuildSettings {
+        let mut defines = parent.defines.clone();
+        defines.extend(self.defines.iter().cloned());
+
+        BuildSettings {
+            defines,
+            cc: self.cc.clone().or_else(|| parent.cc.clone()),
+            cxx: self.cxx.clone().or_else(|| parent.cxx.clone()),
+        }
+    }
+}
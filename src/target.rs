@@ -0,0 +1,224 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Table;
+use files;
+use project::ProjectClass;
+use tomlvalue::{toml_table, toml_value_str};
+use ClinkError;
+
+/// An extra project built from part of a package's sources, such as a binary, example or test.
+#[derive(Clone, Debug)]
+pub struct Target {
+    pub name: String,
+    pub kind: TargetKind,
+    pub class: ProjectClass,
+    /// The source file or folder this target is built from.
+    pub path: PathBuf,
+}
+
+/// The kinds of targets a project file can declare next to the project itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TargetKind {
+    Bin, Example, Test
+}
+
+impl TargetKind {
+    pub fn all() -> [TargetKind; 3] {
+        [TargetKind::Bin, TargetKind::Example, TargetKind::Test]
+    }
+
+    /// The key of the array of tables this kind is declared in.
+    pub fn key(&self) -> &'static str {
+        match *self {
+            TargetKind::Bin => "bin",
+            TargetKind::Example => "example",
+            TargetKind::Test => "test",
+        }
+    }
+
+    /// The folder targets of this kind are in by default, relative to the project.
+    pub fn folder(&self) -> &'static str {
+        match *self {
+            TargetKind::Bin => "src/bin",
+            TargetKind::Example => "examples",
+            TargetKind::Test => "tests",
+        }
+    }
+
+    pub fn class(&self) -> ProjectClass {
+        match *self {
+            TargetKind::Bin | TargetKind::Example => ProjectClass::Application,
+            TargetKind::Test => ProjectClass::Test,
+        }
+    }
+
+    /// Check if targets of this kind can use the project's dev-dependencies.
+    pub fn uses_dev_dependencies(&self) -> bool {
+        *self != TargetKind::Bin
+    }
+
+    /// The name for a target found in the default folder rather than declared. Binaries keep
+    /// their own name, examples and tests are prefixed with the project name.
+    fn discovered_name(&self, project_name: &str, stem: &str) -> String {
+        match *self {
+            TargetKind::Bin => stem.into(),
+            TargetKind::Example | TargetKind::Test => format!("{}-{}", project_name, stem),
+        }
+    }
+}
+
+/// Read all entries of a kind of target from a project file. If there are none, every source
+/// file and folder directly in the kind's default folder becomes a target instead.
+pub fn parse_targets(
+    toml: &Table, kind: TargetKind, project_path: &Path, project_name: &str
+) -> Result<Vec<Target>, ClinkError> {
+    let folder = files::clone_push_path(project_path, kind.folder());
+
+    let entries = match toml.get(kind.key()) {
+        Some(entries) => try!(entries.as_slice().ok_or_else(||
+            ClinkError::InvalidProjectFile(format!("{} is invalid type (expected array of tables)", kind.key()))
+        )),
+        None => return Ok(discover_targets(kind, &folder, project_name)),
+    };
+
+    let mut targets = Vec::new();
+    for entry in entries {
+        let entry = try!(toml_table(entry, kind.key()));
+        let name: String = try!(toml_value_str(entry, "name")).into();
+
+        // Default to a folder or source file in the default folder named after the target
+        let path = if entry.contains_key("path") {
+            files::clone_push_path(project_path, try!(toml_value_str(entry, "path")))
+        } else {
+            let target_folder = files::clone_push_path(&folder, &name);
+            if target_folder.is_dir() {
+                target_folder
+            } else {
+                files::clone_push_path(&folder, &format!("{}.cpp", name))
+            }
+        };
+
+        if !path.exists() {
+            return Err(ClinkError::InvalidProjectStructure(
+                path, format!("Could not find the sources for {} \"{}\"", kind.key(), name)
+            ));
+        }
+
+        targets.push(Target {
+            name,
+            kind,
+            class: kind.class(),
+            path,
+        });
+    }
+
+    Ok(targets)
+}
+
+fn discover_targets(kind: TargetKind, folder: &Path, project_name: &str) -> Vec<Target> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(folder) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(_) => return Vec::new(),
+    };
+    paths.sort();
+
+    paths.into_iter()
+        .filter(|p| p.is_dir() || p.extension().map(|e| e == "cpp" || e == "c").unwrap_or(false))
+        .map(|path| {
+            let stem = path.file_stem().unwrap().to_string_lossy().to_string();
+            Target {
+                name: kind.discovered_name(project_name, &stem),
+                kind,
+                class: kind.class(),
+                path,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::path::Path;
+    use files::TempDir;
+    use project::ProjectClass;
+    use tomlvalue::parse_toml;
+    use super::{Target, TargetKind, parse_targets};
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path).unwrap();
+    }
+
+    fn parse(root: &Path, kind: TargetKind, manifest: &str) -> Result<Vec<Target>, String> {
+        let toml = parse_toml(Path::new("Clink.toml"), manifest).unwrap();
+        parse_targets(&toml, kind, root, "Lib").map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn parse_targets_reads_test_entries() {
+        let dir = TempDir::new("target-entries");
+        let root = dir.path();
+        fs::create_dir_all(root.join("tests").join("math")).unwrap();
+        touch(&root.join("tests").join("io.cpp"));
+        touch(&root.join("tests").join("unused.cpp"));
+        touch(&root.join("check").join("all.cpp"));
+
+        let targets = parse(root, TargetKind::Test, concat!(
+            "[[test]]\nname = \"math\"\n",
+            "[[test]]\nname = \"io\"\n",
+            "[[test]]\nname = \"all\"\npath = \"check/all.cpp\"\n",
+        )).unwrap();
+        let found: Vec<(&str, &Path)> = targets.iter().map(|t| (t.name.as_str(), t.path.as_path())).collect();
+        assert_eq!(found, vec!(
+            ("math", root.join("tests").join("math").as_path()),
+            ("io", root.join("tests").join("io.cpp").as_path()),
+            ("all", root.join("check").join("all.cpp").as_path()),
+        ));
+        assert!(targets.iter().all(|t| t.kind == TargetKind::Test && t.class == ProjectClass::Test));
+
+        let error = parse(root, TargetKind::Test, "[[test]]\nname = \"missing\"\n").unwrap_err();
+        assert!(error.contains("Could not find the sources for test \"missing\""), "{}", error);
+        let error = parse(root, TargetKind::Test, "[test]\nname = \"math\"\n").unwrap_err();
+        assert!(error.contains("test is invalid type (expected array of tables)"), "{}", error);
+    }
+
+    #[test]
+    fn parse_targets_discovers_tests_folder() {
+        let dir = TempDir::new("target-discover");
+        let root = dir.path();
+        fs::create_dir_all(root.join("tests").join("math")).unwrap();
+        touch(&root.join("tests").join("io.cpp"));
+        touch(&root.join("tests").join("parse.c"));
+        touch(&root.join("tests").join("helpers.hpp"));
+        touch(&root.join("tests").join("README.md"));
+
+        let targets = parse(root, TargetKind::Test, "").unwrap();
+        let names: Vec<&str> = targets.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!("Lib-io", "Lib-math", "Lib-parse"));
+
+        // Declaring any test turns discovery off
+        let targets = parse(root, TargetKind::Test, "[[test]]\nname = \"io\"\n").unwrap();
+        assert_eq!(targets.len(), 1);
+        assert!(parse(&root.join("nothing"), TargetKind::Test, "").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_targets_names_discovered_binaries_and_examples() {
+        let dir = TempDir::new("target-kinds");
+        let root = dir.path();
+        touch(&root.join("src").join("bin").join("packer.cpp"));
+        fs::create_dir_all(root.join("src").join("bin").join("viewer")).unwrap();
+        touch(&root.join("examples").join("triangle.cpp"));
+
+        let names = |kind| -> Vec<String> {
+            parse(root, kind, "").unwrap().into_iter().map(|t| t.name).collect()
+        };
+        assert_eq!(names(TargetKind::Bin), vec!("packer", "viewer"));
+        assert_eq!(names(TargetKind::Example), vec!("Lib-triangle"));
+
+        let examples = parse(root, TargetKind::Example, "").unwrap();
+        assert_eq!(examples[0].class, ProjectClass::Application);
+        assert!(TargetKind::Example.uses_dev_dependencies() && !TargetKind::Bin.uses_dev_dependencies());
+    }
+}
@@ -1,4 +1,5 @@
-use toml::{Table, Value};
+use std::path::Path;
+use toml::{Parser, Table, Value};
 use ClinkError;
 
 pub fn toml_value<'a>(table: &'a Table, value_name: &str) -> Result<&'a Value, ClinkError> {
@@ -26,3 +27,71 @@ pub fn toml_value_str<'a>(table: &'a Table, value_name: &str) -> Result<&'a str,
             ClinkError::InvalidProjectFile(format!("{} is invalid type (expected string)", value_name))
         )
 }
+
+pub fn toml_value_str_array(table: &Table, value_name: &str) -> Result<Vec<String>, ClinkError> {
+    let array = try!(try!(toml_value(table, value_name))
+        .as_slice()
+        .ok_or_else(||
+            ClinkError::InvalidProjectFile(format!("{} is invalid type (expected array)", value_name))
+        )
+    );
+
+    let mut strings = Vec::new();
+    for value in array {
+        let string = try!(value.as_str()
+            .ok_or_else(||
+                ClinkError::InvalidProjectFile(format!("{} contains invalid type (expected string)", value_name))
+            )
+        );
+        strings.push(string.into());
+    }
+
+    Ok(strings)
+}
+
+pub fn toml_value_bool(table: &Table, value_name: &str) -> Result<bool, ClinkError> {
+    try!(toml_value(table, value_name))
+        .as_bool()
+        .ok_or_else(||
+            ClinkError::InvalidProjectFile(format!("{} is invalid type (expected boolean)", value_name))
+        )
+}
+
+/// Parse the text of a toml file, syntax errors point at the line and column in the file.
+pub fn parse_toml(path: &Path, text: &str) -> Result<Table, ClinkError> {
+    let mut parser = Parser::new(text);
+    match parser.parse() {
+        Some(table) => Ok(table),
+        None => {
+            let (offset, message) = parser.errors.first()
+                .map(|e| (e.lo, e.desc.clone()))
+                .unwrap_or((0, "Unknown error".into()));
+            let (line, column) = parser.to_linecol(offset);
+            Err(ClinkError::TomlSyntax {
+                path: path.to_path_buf(),
+                line: line + 1,
+                column: column + 1,
+                message,
+                snippet: text.lines().nth(line).unwrap_or("").into(),
+            })
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use super::parse_toml;
+    use ClinkError;
+
+    #[test]
+    fn parse_toml_reports_line_of_error() {
+        match parse_toml(Path::new("Clink.toml"), "[package]\nname = \"A\"\nversion = 1.0.\n") {
+            Err(ClinkError::TomlSyntax { line, ref snippet, .. }) => {
+                assert_eq!(line, 3);
+                assert_eq!(snippet, "version = 1.0.");
+            },
+            _ => panic!("Expected a syntax error"),
+        }
+    }
+}
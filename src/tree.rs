@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+use graph::{ProjectGraph, ResolvedProject};
+use ClinkError;
+
+/// Render the dependency tree of every root in a graph, the way `cargo tree` does. Projects that
+/// were already printed are marked with (*) instead of being printed again, and targets such as
+/// tests are shown below the project they belong to.
+pub fn render_tree(graph: &ProjectGraph) -> String {
+    let mut writer = TreeWriter::new(graph, false);
+    for (i, root) in graph.roots().iter().enumerate() {
+        if i != 0 {
+            writer.out.push('\n');
+        }
+        if let Some(root) = graph.find(root) {
+            writer.write(root, None, "", "");
+        }
+    }
+    writer.out
+}
+
+/// Render the projects that depend on a project, all the way up to the roots.
+pub fn render_inverted_tree(graph: &ProjectGraph, name: &str) -> Result<String, ClinkError> {
+    let project = try!(find(graph, name));
+    let mut writer = TreeWriter::new(graph, true);
+    writer.write(project, None, "", "");
+    Ok(writer.out)
+}
+
+/// Render the graph in Graphviz's dot format. If a project is given, only it and the projects
+/// depending on it are included.
+pub fn render_dot(graph: &ProjectGraph, invert: Option<&str>) -> Result<String, ClinkError> {
+    let included: HashSet<String> = match invert {
+        Some(name) => {
+            let mut included = HashSet::new();
+            included.insert(try!(find(graph, name)).name.clone());
+
+            // Projects come after their dependencies, so one pass finds all dependents
+            for project in graph.projects() {
+                if edges(project).iter().any(|(dep, _)| included.contains(dep)) {
+                    included.insert(project.name.clone());
+                }
+            }
+            included
+        },
+        None => graph.projects().iter().map(|p| p.name.clone()).collect(),
+    };
+
+    let mut dot = format!("digraph \"{}\" {{\n", escape(graph.name()));
+    for project in graph.projects().iter().filter(|p| included.contains(&p.name)) {
+        // Labels show the version and kind of project on lines below the name
+        let mut label = escape(&project.name);
+        if let Some(ref version) = project.version {
+            label.push_str(&format!("\\nv{}", version));
+        }
+        label.push_str(&format!("\\n{}", project.class.to_string()));
+        dot.push_str(&format!("    \"{}\" [label=\"{}\"];\n", escape(&project.name), label));
+
+        for (dep, cfg) in edges(project) {
+            if !included.contains(&dep) {
+                continue;
+            }
+            match cfg {
+                Some(cfg) => dot.push_str(&format!(
+                    "    \"{}\" -> \"{}\" [label=\"{}\", style=dashed];\n", escape(&project.name), escape(&dep), escape(&cfg)
+                )),
+                None => dot.push_str(&format!("    \"{}\" -> \"{}\";\n", escape(&project.name), escape(&dep))),
+            }
+        }
+    }
+    dot.push_str("}\n");
+
+    Ok(dot)
+}
+
+struct TreeWriter<'a> {
+    graph: &'a ProjectGraph,
+    inverted: bool,
+    printed: HashSet<String>,
+    out: String,
+}
+
+impl<'a> TreeWriter<'a> {
+    fn new(graph: &'a ProjectGraph, inverted: bool) -> Self {
+        TreeWriter {
+            graph,
+            inverted,
+            printed: HashSet::new(),
+            out: String::new(),
+        }
+    }
+
+    fn write(&mut self, project: &ResolvedProject, cfg: Option<String>, line_prefix: &str, child_prefix: &str) {
+        self.out.push_str(line_prefix);
+        self.out.push_str(&describe(project));
+        if let Some(cfg) = cfg {
+            self.out.push_str(&format!(" ({})", cfg));
+        }
+
+        // Only print what's below a project the first time we see it
+        if !self.printed.insert(project.name.clone()) {
+            self.out.push_str(" (*)\n");
+            return;
+        }
+        self.out.push('\n');
+
+        let children = self.children(project);
+        for (i, (child, cfg)) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let line_prefix = format!("{}{}", child_prefix, if last { "└── " } else { "├── " });
+            let child_prefix = format!("{}{}", child_prefix, if last { "    " } else { "│   " });
+            self.write(child, cfg.clone(), &line_prefix, &child_prefix);
+        }
+    }
+
+    /// The projects below a project in the tree, its dependencies or, inverted, its dependents.
+    fn children(&self, project: &ResolvedProject) -> Vec<(&'a ResolvedProject, Option<String>)> {
+        if self.inverted {
+            self.graph.projects().iter()
+                .filter_map(|p| edges(p).into_iter()
+                    .find(|(dep, _)| dep == &project.name)
+                    .map(|(_, cfg)| (p, cfg))
+                )
+                .collect()
+        } else {
+            // A target already sits below its package, so it only shows what it adds to it, such
+            // as dev-dependencies
+            let package = project.package.as_ref().and_then(|p| self.graph.find(p));
+            let mut children: Vec<(&'a ResolvedProject, Option<String>)> = edges(project).into_iter()
+                .filter(|edge| match package {
+                    Some(package) => edge.0 != package.name && !edges(package).contains(edge),
+                    None => true,
+                })
+                .filter_map(|(dep, cfg)| self.graph.find(&dep).map(|p| (p, cfg)))
+                .collect();
+            children.extend(self.graph.projects().iter()
+                .filter(|p| p.package.as_ref() == Some(&project.name))
+                .map(|p| (p, None))
+            );
+            children
+        }
+    }
+}
+
+/// The dependencies of a project, with the condition of those only used on some platforms.
+fn edges(project: &ResolvedProject) -> Vec<(String, Option<String>)> {
+    let mut edges: Vec<(String, Option<String>)> = project.dependencies.iter()
+        .map(|d| (d.clone(), None))
+        .collect();
+    edges.extend(project.platform_dependencies.iter()
+        .map(|(cfg, d)| (d.clone(), Some(format!("cfg({})", cfg))))
+    );
+    edges
+}
+
+fn describe(project: &ResolvedProject) -> String {
+    let mut description = project.name.clone();
+    if let Some(ref version) = project.version {
+        description.push_str(&format!(" v{}", version));
+    }
+    description.push_str(&format!(" ({}) [{}]", project.path.display(), project.class.to_string()));
+    description
+}
+
+fn find<'a>(graph: &'a ProjectGraph, name: &str) -> Result<&'a ResolvedProject, ClinkError> {
+    graph.find(name).ok_or_else(|| ClinkError::InvalidProjectFile(
+        format!("\"{}\" is not a project in this project's dependency graph", name)
+    ))
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use files::TempDir;
+    use graph::ProjectGraph;
+    use super::render_tree;
+
+    #[test]
+    fn render_tree_shows_targets_below_their_project() {
+        let dir = TempDir::new("tree");
+        let root = dir.path();
+        for &(name, manifest) in &[
+            ("Catch", "[package]\nname = \"Catch\"\ntype = \"library\"\n"),
+            ("Lib", concat!(
+                "[package]\nname = \"Lib\"\ntype = \"library\"\n",
+                "[dev-dependencies]\nCatch = \"../Catch\"\n",
+                "[[test]]\nname = \"LibTests\"\npath = \"tests\"\n",
+            )),
+        ] {
+            fs::create_dir_all(root.join(name)).unwrap();
+            File::create(root.join(name).join("Clink.toml")).unwrap().write_all(manifest.as_bytes()).unwrap();
+        }
+        fs::create_dir_all(root.join("Lib").join("tests")).unwrap();
+
+        let graph = ProjectGraph::load(root.join("Lib")).unwrap();
+
+        let tree = render_tree(&graph);
+        let lines: Vec<&str> = tree.lines().map(|l| l.split(" (").next().unwrap()).collect();
+        assert_eq!(lines, vec!("Lib", "└── LibTests", "    └── Catch"));
+    }
+}
@@ -1,10 +1,11 @@
 use std::path::{Path, PathBuf};
 use std::fs::File;
 use std::io::Write;
-use uuid::Uuid;
 use xml::writer::{XmlEvent, EmitterConfig};
+use files;
 use visualstudio::{ProjFiles};
 use wincanonicalize::wincanonicalize;
+use ClinkError;
 
 struct FileEntry {
     location: PathBuf,
@@ -40,11 +41,27 @@ fn add_filter_recursive(filters: &mut Vec<String>, filter: &String) {
     }
 }
 
-pub fn generate_filters<Pr: AsRef<Path>, Pt: AsRef<Path>>(project_root: Pr, files: &ProjFiles, target: Pt) {
+pub fn generate_filters<Pr: AsRef<Path>, Pt: AsRef<Path>>(
+    project_root: Pr, files: &ProjFiles, target: Pt
+) -> Result<(), ClinkError> {
+    let target = target.as_ref();
+    let filedata = try!(render_filters(project_root, files, target.parent().unwrap_or(Path::new("."))));
+
+    // Finally, write the generated file to disk
+    File::create(target)
+        .and_then(|mut file| file.write_all(filedata.as_bytes()))
+        .map_err(|e| ClinkError::WriteFailed(target.to_path_buf(), e))
+}
+
+/// The contents of the .vcxproj.filters file for a project's files, with their paths relative to
+/// the folder the file is in.
+pub fn render_filters<Pr: AsRef<Path>>(
+    project_root: Pr, files: &ProjFiles, folder: &Path
+) -> Result<String, ClinkError> {
     // We're interested in all files, but we do need to know what they are
     let mut all_files = Vec::new();
     for file in &files.compile {
-        let file = wincanonicalize(file);
+        let file = try!(wincanonicalize(file));
 
         all_files.push(FileEntry {
             location: file,
@@ -53,7 +70,7 @@ pub fn generate_filters<Pr: AsRef<Path>, Pt: AsRef<Path>>(project_root: Pr, file
         });
     }
     for file in &files.include {
-        let file = wincanonicalize(file);
+        let file = try!(wincanonicalize(file));
 
         all_files.push(FileEntry {
             location: file,
@@ -63,16 +80,15 @@ pub fn generate_filters<Pr: AsRef<Path>, Pt: AsRef<Path>>(project_root: Pr, file
     }
 
     // Get the canonical root of the project
-    let root = wincanonicalize(project_root);
+    let root = try!(wincanonicalize(project_root));
 
     // Generate a list of needed filters and files associated with filters
     let mut filters: Vec<String> = Vec::new();
     for file in &mut all_files {
         // Find the filter string this file needs to be in
-        assert!(file.location.starts_with(&root),
-            "Internal Error: {:?} does not start with {:?}", file.location, root
-        );
-        let mut filter = file.location.strip_prefix(&root).unwrap().to_owned();
+        let mut filter = try!(file.location.strip_prefix(&root).map_err(|_| ClinkError::InvalidProjectStructure(
+            file.location.clone(), format!("File is not in the project folder {}", root.display())
+        ))).to_owned();
         filter.pop();
         let filter = format!("{}", filter.display());
 
@@ -101,7 +117,7 @@ pub fn generate_filters<Pr: AsRef<Path>, Pt: AsRef<Path>>(project_root: Pr, file
             ).unwrap();
 
             w.write(XmlEvent::start_element("UniqueIdentifier")).unwrap();
-            let uuid = format!("{{{}}}", Uuid::new_v4().hyphenated());
+            let uuid = format!("{{{}}}", files::stable_uuid(&format!("clink-filter:{}", filter)).hyphenated());
             w.write(XmlEvent::characters(&uuid)).unwrap();
             w.write(XmlEvent::end_element()).unwrap();
 
@@ -114,7 +130,7 @@ pub fn generate_filters<Pr: AsRef<Path>, Pt: AsRef<Path>>(project_root: Pr, file
         for file in all_files {
             let class: &str = &file.class;
             w.write(XmlEvent::start_element(class)
-                .attr("Include", &format!("{}", file.location.display()))
+                .attr("Include", &format!("{}", files::resolved_relative_path(folder, &file.location).display()))
             ).unwrap();
 
             w.write(XmlEvent::start_element("Filter")).unwrap();
@@ -128,11 +144,6 @@ pub fn generate_filters<Pr: AsRef<Path>, Pt: AsRef<Path>>(project_root: Pr, file
         w.write(XmlEvent::end_element()).unwrap();
     }
 
-    // Finally, write the generated file to disk
-    let mut file = File::create(target).unwrap();
-    write!(file, "{}", ::std::str::from_utf8(&b).unwrap()).unwrap();
-
-
     // Write the compile files
     /*let mut compiled = String::new();
     for filename in &files.compile {
@@ -152,4 +163,6 @@ pub fn generate_filters<Pr: AsRef<Path>, Pt: AsRef<Path>>(project_root: Pr, file
         include.push_str("</ClInclude>\n");
     }
     filedata = filedata.replace("{INCLUDE_FILES}", &include);*/
+
+    Ok(String::from_utf8(b).unwrap())
 }
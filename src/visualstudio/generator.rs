@@ -0,0 +1,211 @@
+use std::path::PathBuf;
+use cfg::Platform;
+use generator::{GeneratedFiles, Generator};
+use graph::{ProjectGraph, ResolvedProject};
+use project::ProjectClass;
+use files;
+use visualstudio::{self, ProjFiles, SlnFile, VcxprojFile, ProjDesc, VcxprojPlatform, VcxprojType};
+use ClinkError;
+
+/// Generates a Visual Studio solution for the graph's root project or workspace, with a .vcxproj and .vcxproj.filters
+/// file next to every project's Clink.toml. Git dependencies get theirs in target/vs/ instead, so the shared checkout
+/// isn't changed.
+#[derive(Default)]
+pub struct VisualStudioGenerator;
+
+impl VisualStudioGenerator {
+    pub fn new() -> Self {
+        VisualStudioGenerator
+    }
+
+    /// The platform a vcxproj platform builds for, to evaluate conditions against.
+    pub fn platform(platform: VcxprojPlatform) -> Platform {
+        match platform {
+            VcxprojPlatform::Win32 => Platform::new("windows", "x86"),
+            VcxprojPlatform::X64 => Platform::new("windows", "x86_64"),
+        }
+    }
+
+    /// The folder a project's .vcxproj and .vcxproj.filters files are written to.
+    pub fn project_folder(graph: &ProjectGraph, project: &ResolvedProject) -> PathBuf {
+        if project.git_checkout {
+            let mut folder = files::clone_push_path(graph.path(), "target");
+            folder.push("vs");
+            folder.push(&project.name);
+            folder
+        } else {
+            project.path.clone()
+        }
+    }
+}
+
+impl Generator for VisualStudioGenerator {
+    fn generate_files(&self, graph: &ProjectGraph) -> Result<GeneratedFiles, ClinkError> {
+        let mut generated = GeneratedFiles::new();
+
+        // Every platform gets its own dependencies and settings
+        let platforms: Vec<(VcxprojPlatform, ProjectGraph)> = VcxprojPlatform::all().iter()
+            .map(|p| (*p, graph.for_platform(&VisualStudioGenerator::platform(*p))))
+            .collect();
+
+        // Generate vcxprojs for all projects, the graph gives them to us dependencies-first
+        let mut descs: Vec<ProjDesc> = Vec::new();
+        for project in graph.projects() {
+            // Leave out projects that aren't used on any of our platforms
+            let found: Vec<_> = platforms.iter()
+                .filter_map(|&(platform, ref graph)| graph.find(&project.name).map(|p| (platform, p)))
+                .collect();
+            if found.is_empty() {
+                continue;
+            }
+
+            // Get the project type for our clink project type string
+            let class = match project.class {
+                ProjectClass::Application | ProjectClass::Test => VcxprojType::Application,
+                ProjectClass::Library => VcxprojType::StaticLibrary,
+            };
+
+            // Create the project file representation
+            let mut vcxproj = VcxprojFile::new(project.name.clone(), class);
+            for file in &project.compile_files {
+                vcxproj.add_compile(file.clone());
+            }
+            for file in &project.include_files {
+                vcxproj.add_include(file.clone());
+            }
+            for &(platform, found) in &found {
+                for path in &found.include_paths {
+                    vcxproj.add_include_path(platform, path.clone());
+                }
+                for define in &found.settings.defines {
+                    vcxproj.add_define(platform, define.clone());
+                }
+            }
+
+            // Add a reference for every dependency, only on the platform using it if not all do
+            let mut dependencies: Vec<&String> = Vec::new();
+            for &(_, found) in &found {
+                for dep in &found.dependencies {
+                    if !dependencies.contains(&dep) {
+                        dependencies.push(dep);
+                    }
+                }
+            }
+            for dep in dependencies {
+                let desc = try!(descs.iter().find(|d| &d.name == dep).ok_or_else(|| ClinkError::InvalidProjectFile(
+                    format!("Project \"{}\" depends on \"{}\", which is not in the solution", project.name, dep)
+                )));
+                let using: Vec<VcxprojPlatform> = found.iter()
+                    .filter(|&&(_, p)| p.dependencies.contains(dep))
+                    .map(|&(platform, _)| platform)
+                    .collect();
+                if using.len() == VcxprojPlatform::all().len() {
+                    vcxproj.add_reference(desc.clone());
+                } else {
+                    for platform in using {
+                        vcxproj.add_platform_reference(desc.clone(), platform);
+                    }
+                }
+            }
+
+            // Add the vcxproj and vcxproj.filters to the generated files
+            let folder = VisualStudioGenerator::project_folder(graph, project);
+            let filename = format!("{}.vcxproj", project.name);
+            let path = files::clone_push_path(&folder, &filename);
+            generated.insert(path.clone(), vcxproj.render(&folder));
+            let desc = vcxproj.desc(path);
+            let filename = format!("{}.vcxproj.filters", project.name);
+            let files = ProjFiles {
+                compile: project.compile_files.clone(),
+                include: project.include_files.clone(),
+            };
+            generated.insert(
+                files::clone_push_path(&folder, &filename), try!(visualstudio::render_filters(&project.path, &files, &folder))
+            );
+
+            descs.push(desc);
+        }
+
+        // Add the sln referencing all of them
+        let mut sln = SlnFile::new();
+        for desc in descs {
+            sln.add_project(desc);
+        }
+        let filename = format!("{}.sln", graph.name());
+        generated.insert(files::clone_push_path(graph.path(), &filename), sln.render(graph.path()));
+
+        Ok(generated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::Path;
+    use files::TempDir;
+    use generator::Generator;
+    use graph::ProjectGraph;
+    use project::{Project, ProjectClass};
+    use super::VisualStudioGenerator;
+
+    #[test]
+    fn generate_files_keeps_output_in_memory() {
+        let dir = TempDir::new("vs");
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        File::create(root.join("src/main.cpp")).unwrap();
+
+        let project = Project::builder("Game".into(), root)
+            .class(ProjectClass::Application)
+            .define("GAME_DEBUG".into())
+            .compile_file(root.join("src/main.cpp"))
+            .build();
+        let graph = ProjectGraph::resolve(&project).unwrap();
+        let files = VisualStudioGenerator::new().generate_files(&graph).unwrap();
+
+        assert!(!root.join("Game.sln").exists());
+        assert_eq!(files.len(), 3);
+        let vcxproj = files.iter().find(|&(p, _)| p.ends_with("Game.vcxproj")).unwrap().1;
+        assert!(vcxproj.contains("GAME_DEBUG;"));
+    }
+
+    #[test]
+    fn generate_files_writes_paths_relative_to_each_file() {
+        let dir = TempDir::new("vs-relative");
+        let root = dir.path();
+        write_file(&root.join("Game").join("Clink.toml"), concat!(
+            "[package]\nname = \"Game\"\ntype = \"application\"\n",
+            "[dependencies]\nMath = { path = \"../Math\" }\n",
+        ));
+        write_file(&root.join("Game").join("src").join("main.cpp"), "");
+        write_file(&root.join("Math").join("Clink.toml"), "[package]\nname = \"Math\"\ntype = \"library\"\n");
+        write_file(&root.join("Math").join("src").join("math.cpp"), "");
+
+        let project = Project::open(root.join("Game")).unwrap();
+        let graph = ProjectGraph::resolve(&project).unwrap();
+        let files = VisualStudioGenerator::new().generate_files(&graph).unwrap();
+
+        let absolute = root.display().to_string();
+        for (path, contents) in &files {
+            assert!(!contents.contains(&absolute), "{} has an absolute path", path.display());
+        }
+        let file = |name: &str| files.iter().find(|&(p, _)| p.ends_with(name)).unwrap().1;
+        let math = Path::new("..").join("Math");
+        let game = file("Game.vcxproj");
+        assert!(game.contains(&format!("<ClCompile Include=\"{}\" />", Path::new("src").join("main.cpp").display())));
+        assert!(game.contains(&format!("\"{}\"", math.join("Math.vcxproj").display())), "{}", game);
+        assert!(game.contains("<IncludePath>include;"), "{}", game);
+        let sln = file("Game.sln");
+        assert!(sln.contains("\"Game.vcxproj\""), "{}", sln);
+        assert!(sln.contains(&format!("\"{}\"", math.join("Math.vcxproj").display())), "{}", sln);
+        let filters = file("Math.vcxproj.filters");
+        let math_cpp = Path::new("src").join("math.cpp");
+        assert!(filters.contains(&format!("Include=\"{}\"", math_cpp.display())), "{}", filters);
+    }
+
+    fn write_file(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path).unwrap().write_all(contents.as_bytes()).unwrap();
+    }
+}
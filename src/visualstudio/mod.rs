@@ -1,4 +1,5 @@
 mod filters;
+mod generator;
 mod projfiles;
 mod slnfile;
 mod vcxprojfile;
@@ -6,10 +7,11 @@ mod vcxprojfile;
 use std::path::PathBuf;
 use uuid::Uuid;
 
-pub use self::filters::generate_filters;
+pub use self::filters::{generate_filters, render_filters};
+pub use self::generator::VisualStudioGenerator;
 pub use self::projfiles::ProjFiles;
 pub use self::slnfile::SlnFile;
-pub use self::vcxprojfile::{VcxprojFile, VcxprojType};
+pub use self::vcxprojfile::{VcxprojFile, VcxprojPlatform, VcxprojType};
 
 #[derive(Clone, Debug)]
 pub struct ProjDesc {
@@ -1,25 +1,35 @@
 use std::path::PathBuf;
 use walkdir::WalkDir;
 use wincanonicalize::wincanonicalize;
+use ClinkError;
 
+#[derive(Clone, Debug, Default)]
 pub struct ProjFiles {
     pub compile: Vec<PathBuf>,
     pub include: Vec<PathBuf>,
 }
 
 impl ProjFiles {
-    pub fn scan(path: &PathBuf) -> ProjFiles {
+    /// Find all source files in a folder, skipping anything in the excluded files and folders.
+    pub fn scan(path: &PathBuf, excluded: &[PathBuf]) -> Result<ProjFiles, ClinkError> {
         let mut compile = Vec::new();
         let mut include = Vec::new();
 
+        let mut canonical_excluded = Vec::new();
+        for excluded in excluded.iter().filter(|p| p.exists()) {
+            canonical_excluded.push(try!(wincanonicalize(excluded)));
+        }
+        let excluded = canonical_excluded;
+
         for file in WalkDir::new(path) {
-            let file = file.unwrap();
+            let file = try!(file.map_err(|e| ClinkError::Io(e.path().unwrap_or(path).to_path_buf(), e.into())));
             let file = file.path();
 
             // Only go over files
             if !file.is_file() { continue; }
 
-            let file = wincanonicalize(file);
+            let file = try!(wincanonicalize(file));
+            if excluded.iter().any(|e| file.starts_with(e)) { continue; }
 
             // Different behavior for different files
             let extension: String = file.extension()
@@ -36,9 +46,13 @@ impl ProjFiles {
             // Ignore anything else
         }
 
-        ProjFiles {
+        // Folders aren't read in the same order everywhere, sort so generated files don't change
+        compile.sort();
+        include.sort();
+
+        Ok(ProjFiles {
             compile: compile,
             include: include,
-        }
+        })
     }
 }
@@ -1,8 +1,11 @@
 use std::path::Path;
 use std::fs::File;
-use std::io::Write;
+use std::io::{self, Write};
+use files;
 use visualstudio::{ProjDesc, escape};
+use ClinkError;
 
+#[derive(Default)]
 pub struct SlnFile {
     projects: Vec<ProjDesc>,
 }
@@ -18,24 +21,41 @@ impl SlnFile {
         self.projects.push(proj);
     }
 
-    pub fn write_to<P: AsRef<Path>>(&self, path: P) {
-        let mut file = File::create(path).unwrap();
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<(), ClinkError> {
+        let path = path.as_ref();
+        File::create(path)
+            .and_then(|mut file| self.write(&mut file, path.parent().unwrap_or(Path::new("."))))
+            .map_err(|e| ClinkError::WriteFailed(path.to_path_buf(), e))
+    }
 
+    /// Write the solution into anything that takes bytes, such as a file or a buffer. Project
+    /// paths are written relative to the folder the solution is in.
+    pub fn write<W: Write>(&self, file: &mut W, folder: &Path) -> io::Result<()> {
         // Generic version metadata
-        writeln!(file, "Microsoft Visual Studio Solution File, Format Version 12.00").unwrap();
-        writeln!(file, "# Visual Studio 14").unwrap();
-        writeln!(file, "VisualStudioVersion = 14.0.25420.1").unwrap();
-        writeln!(file, "MinimumVisualStudioVersion = 10.0.40219.1").unwrap();
+        try!(writeln!(file, "Microsoft Visual Studio Solution File, Format Version 12.00"));
+        try!(writeln!(file, "# Visual Studio 14"));
+        try!(writeln!(file, "VisualStudioVersion = 14.0.25420.1"));
+        try!(writeln!(file, "MinimumVisualStudioVersion = 10.0.40219.1"));
 
         // Write all projects
         for project in &self.projects {
-            writeln!(
+            let path = files::resolved_relative_path(folder, &project.vcxproj_path);
+            try!(writeln!(
                 file, // The hardcoded GUID here is the C++ project type
                 "Project(\"{}\") = \"{}\", \"{}\", \"{{{}}}\"",
                 "{8BC9CEB8-8B4A-11D0-8D11-00A0C91BC942}",
-                project.name, escape(format!("{}", project.vcxproj_path.display())), project.uuid.hyphenated()
-            ).unwrap();
-            writeln!(file, "EndProject").unwrap();
+                project.name, escape(format!("{}", path.display())), project.uuid.hyphenated()
+            ));
+            try!(writeln!(file, "EndProject"));
         }
+
+        Ok(())
+    }
+
+    /// The contents of the solution file, with project paths relative to the folder it's in.
+    pub fn render(&self, folder: &Path) -> String {
+        let mut buffer = Vec::new();
+        self.write(&mut buffer, folder).unwrap();
+        String::from_utf8(buffer).unwrap()
     }
 }
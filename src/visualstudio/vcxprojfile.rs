@@ -1,38 +1,68 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs::File;
-use std::io::Write;
+use std::io::{self, Write};
 use uuid::Uuid;
+use files;
 use visualstudio::{ProjDesc, escape};
+use ClinkError;
 
 pub enum VcxprojType {
     Application, StaticLibrary
 }
 
+/// The platforms every vcxproj has configurations for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VcxprojPlatform {
+    Win32, X64
+}
+
+impl VcxprojPlatform {
+    pub fn all() -> [VcxprojPlatform; 2] {
+        [VcxprojPlatform::Win32, VcxprojPlatform::X64]
+    }
+
+    /// The name of the platform in Visual Studio.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            VcxprojPlatform::Win32 => "Win32",
+            VcxprojPlatform::X64 => "x64",
+        }
+    }
+}
+
 pub struct VcxprojFile {
     name: String,
     class: VcxprojType,
     uuid: Uuid,
-    include_path: Vec<PathBuf>,
+    include_path: Vec<(VcxprojPlatform, PathBuf)>,
+    defines: Vec<(VcxprojPlatform, String)>,
     include_files: Vec<PathBuf>,
     compile_files: Vec<PathBuf>,
-    references: Vec<ProjDesc>,
+    /// Referenced projects, and the only platform they're referenced on if not all.
+    references: Vec<(ProjDesc, Option<VcxprojPlatform>)>,
 }
 
 impl VcxprojFile {
     pub fn new(name: String, class: VcxprojType) -> Self {
+        let uuid = files::stable_uuid(&format!("clink-project:{}", name));
         VcxprojFile {
             name: name,
             class: class,
-            uuid: Uuid::new_v4(),
+            uuid,
             include_path: Vec::new(),
+            defines: Vec::new(),
             include_files: Vec::new(),
             compile_files: Vec::new(),
             references: Vec::new(),
         }
     }
 
-    pub fn add_include_path(&mut self, path: PathBuf) {
-        self.include_path.push(path);
+    pub fn add_include_path(&mut self, platform: VcxprojPlatform, path: PathBuf) {
+        self.include_path.push((platform, path));
+    }
+
+    pub fn add_define(&mut self, platform: VcxprojPlatform, define: String) {
+        self.defines.push((platform, define));
     }
 
     pub fn add_include(&mut self, path: PathBuf) {
@@ -44,11 +74,40 @@ impl VcxprojFile {
     }
 
     pub fn add_reference(&mut self, desc: ProjDesc) {
-        self.references.push(desc);
+        self.references.push((desc, None));
+    }
+
+    /// Reference a project only in the configurations of one platform.
+    pub fn add_platform_reference(&mut self, desc: ProjDesc, platform: VcxprojPlatform) {
+        self.references.push((desc, Some(platform)));
     }
 
-    pub fn write_to<P: Into<PathBuf>>(&self, path: P) -> ProjDesc {
+    pub fn write_to<P: Into<PathBuf>>(&self, path: P) -> Result<ProjDesc, ClinkError> {
         let path: PathBuf = path.into();
+        try!(File::create(&path)
+            .and_then(|mut file| self.write(&mut file, path.parent().unwrap_or(Path::new("."))))
+            .map_err(|e| ClinkError::WriteFailed(path.clone(), e))
+        );
+        Ok(self.desc(path))
+    }
+
+    /// Write the project into anything that takes bytes, such as a file or a buffer. Paths are
+    /// written relative to the folder the project file is in.
+    pub fn write<W: Write>(&self, file: &mut W, folder: &Path) -> io::Result<()> {
+        write!(file, "{}", self.render(folder))
+    }
+
+    /// How the solution and other projects refer to this project, once it's written to a path.
+    pub fn desc(&self, path: PathBuf) -> ProjDesc {
+        ProjDesc {
+            name: self.name.clone(),
+            vcxproj_path: path,
+            uuid: self.uuid,
+        }
+    }
+
+    /// The contents of the project file, with paths relative to the folder it's in.
+    pub fn render(&self, folder: &Path) -> String {
         let mut filedata = String::from(include_str!("./template.vcxproj"));
 
         // TODO: Use an XML library to clean this up and make it safer
@@ -67,18 +126,29 @@ impl VcxprojFile {
         let uuid = format!("{{{}}}", self.uuid.hyphenated());
         filedata = filedata.replace("{UUID}", &uuid);
 
-        // Build and inject the include path
-        let mut include_path_str = String::new();
-        for inc in &self.include_path {
-            include_path_str.push_str(&format!("{}", inc.display()));
-            include_path_str.push(';');
+        // Build and inject the include path and preprocessor definitions of every platform
+        for platform in &VcxprojPlatform::all() {
+            let mut include_path_str = String::new();
+            for (_, inc) in self.include_path.iter().filter(|&&(p, _)| p == *platform) {
+                include_path_str.push_str(&format!("{}", files::resolved_relative_path(folder, inc).display()));
+                include_path_str.push(';');
+            }
+            let placeholder = format!("{{INCLUDE_PATH_{}}}", platform.name().to_uppercase());
+            filedata = filedata.replace(&placeholder, &include_path_str);
+
+            let mut defines_str = String::new();
+            for (_, define) in self.defines.iter().filter(|&&(p, _)| p == *platform) {
+                defines_str.push_str(&escape(define.clone()));
+                defines_str.push(';');
+            }
+            let placeholder = format!("{{DEFINES_{}}}", platform.name().to_uppercase());
+            filedata = filedata.replace(&placeholder, &defines_str);
         }
-        filedata = filedata.replace("{INCLUDE_PATH}", &include_path_str);
 
         // Write the compile files
         let mut compiled = String::new();
         for filename in &self.compile_files {
-            let filename = escape(format!("{}", filename.display()));
+            let filename = escape(format!("{}", files::resolved_relative_path(folder, filename).display()));
             compiled.push_str(&format!("<ClCompile Include=\"{}\" />\n", filename));
         }
         filedata = filedata.replace("{COMPILE_FILES}", &compiled);
@@ -86,29 +156,24 @@ impl VcxprojFile {
         // Write the include files
         let mut include = String::new();
         for filename in &self.include_files {
-            let filename = escape(format!("{}", filename.display()));
+            let filename = escape(format!("{}", files::resolved_relative_path(folder, filename).display()));
             include.push_str(&format!("<ClInclude Include=\"{}\" />\n", filename));
         }
         filedata = filedata.replace("{INCLUDE_FILES}", &include);
 
         // Write the references
         let mut references = String::new();
-        for reference in &self.references {
-            let path = escape(format!("{}", reference.vcxproj_path.display()));
-            references.push_str(&format!("<ProjectReference Include=\"{}\">\n", path));
+        for &(ref reference, platform) in &self.references {
+            let path = escape(format!("{}", files::resolved_relative_path(folder, &reference.vcxproj_path).display()));
+            match platform {
+                Some(platform) => references.push_str(&format!(
+                    "<ProjectReference Include=\"{}\" Condition=\"'$(Platform)'=='{}'\">\n", path, platform.name()
+                )),
+                None => references.push_str(&format!("<ProjectReference Include=\"{}\">\n", path)),
+            }
             references.push_str(&format!("<Project>{}</Project>\n", reference.uuid));
             references.push_str("</ProjectReference>\n");
         }
-        filedata = filedata.replace("{REFERENCES}", &references);
-
-        // Finally, write the generated file to disk
-        let mut file = File::create(&path).unwrap();
-        write!(file, "{}", filedata).unwrap();
-
-        ProjDesc {
-            name: self.name.clone(),
-            vcxproj_path: path,
-            uuid: self.uuid,
-        }
+        filedata.replace("{REFERENCES}", &references)
     }
 }
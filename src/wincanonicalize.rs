@@ -1,11 +1,11 @@
 use std::path::{Path, PathBuf};
+use ClinkError;
 
-pub fn wincanonicalize<P: AsRef<Path>>(value: P) -> PathBuf {
-    let mut canonical = value.as_ref().canonicalize().unwrap();
+pub fn wincanonicalize<P: AsRef<Path>>(value: P) -> Result<PathBuf, ClinkError> {
+    let value = value.as_ref();
+    let canonical = try!(value.canonicalize().map_err(|e| ClinkError::Io(value.to_path_buf(), e)));
 
     // Grrr windows
-    let canonical_str = canonical.to_str().unwrap().to_string();
-    canonical = canonical_str.replace("\\\\?\\", "").into();
-
-    canonical
+    let canonical_str = canonical.to_string_lossy().to_string();
+    Ok(canonical_str.replace("\\\\?\\", "").into())
 }
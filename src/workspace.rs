@@ -0,0 +1,359 @@
+use std::path::{Path, PathBuf};
+use glob::glob;
+use toml::Table;
+use features::FeatureSelection;
+use dependency::{self, Dependency};
+use files;
+use generator::Generator;
+use graph::ProjectGraph;
+use project::{self, Project};
+use settings::BuildSettings;
+use tomlvalue::{toml_table, toml_value_str_array};
+use visualstudio::VisualStudioGenerator;
+use wincanonicalize::wincanonicalize;
+use ClinkError;
+
+/// A set of projects that are generated and built together. A Clink.toml without a [workspace]
+/// table is a workspace with only its own project as member.
+pub struct Workspace {
+    path: PathBuf,
+    name: String,
+    members: Vec<Project>,
+    member_paths: Vec<PathBuf>,
+    settings: BuildSettings,
+    patches: Vec<Dependency>,
+}
+
+impl Workspace {
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<Self, ClinkError> {
+        let path: PathBuf = path.into();
+        let toml = try!(project::read_manifest(&path));
+
+        // A project without a workspace table is a workspace of its own
+        let workspace = match toml.get("workspace") {
+            Some(workspace) => try!(toml_table(workspace, "workspace")),
+            None => {
+                let project = try!(Project::open(&path));
+                return Ok(Workspace {
+                    path: path.clone(),
+                    name: project.name().clone(),
+                    patches: project.patches().clone(),
+                    members: vec!(project),
+                    member_paths: vec!(try!(wincanonicalize(&path))),
+                    settings: BuildSettings::new(),
+                });
+            },
+        };
+
+        // If the workspace root is a project itself, it's a member as well
+        let mut members = Vec::new();
+        if toml.contains_key("package") {
+            members.push(try!(Project::open(&path)));
+        }
+
+        // Find all members matching the patterns
+        for member_path in try!(match_members(&path, workspace)) {
+            members.push(try!(Project::open(member_path)));
+        }
+
+        // The workspace is named after its root project, or its folder if there is none
+        let name = match members.first() {
+            Some(root) if toml.contains_key("package") => root.name().clone(),
+            _ => try!(wincanonicalize(&path)).file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or("Workspace".into()),
+        };
+
+        let patches = try!(dependency::parse_patches(&toml, &path));
+        let mut member_paths = Vec::new();
+        for member in &members {
+            member_paths.push(try!(wincanonicalize(member.path())));
+        }
+        Ok(Workspace {
+            member_paths,
+            path,
+            name,
+            members,
+            settings: try!(BuildSettings::parse(workspace)),
+            patches,
+        })
+    }
+
+    /// Find and open the workspace a folder belongs to, the way cargo does. See
+    /// `find_workspace_root` for how it's found.
+    pub fn discover<P: AsRef<Path>>(start: P) -> Result<Self, ClinkError> {
+        Workspace::open(try!(find_workspace_root(start)))
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn members(&self) -> &Vec<Project> {
+        &self.members
+    }
+
+    /// The [workspace.build] settings, inherited by all members.
+    pub fn settings(&self) -> &BuildSettings {
+        &self.settings
+    }
+
+    /// The dependencies from the root manifest's [patch] table.
+    pub fn patches(&self) -> &Vec<Dependency> {
+        &self.patches
+    }
+
+    /// Check if the project in a folder is a member of this workspace.
+    pub fn is_member(&self, path: &Path) -> bool {
+        match wincanonicalize(path) {
+            Ok(path) => self.member_paths.iter().any(|p| p == &path),
+            Err(_) => false,
+        }
+    }
+
+    /// Resolve all members and their dependencies into one graph. Every package in the graph
+    /// and the exact source it resolved to is recorded in Clink.lock next to the workspace's
+    /// manifest.
+    pub fn resolve(&self) -> Result<ProjectGraph, ClinkError> {
+        self.resolve_with(&FeatureSelection::new(), false)
+    }
+
+    /// Resolve like `resolve`, but fail instead of changing Clink.lock if it's missing or out of
+    /// date.
+    pub fn resolve_locked(&self) -> Result<ProjectGraph, ClinkError> {
+        self.resolve_with(&FeatureSelection::new(), true)
+    }
+
+    /// Resolve with features enabled on the members, optionally failing if Clink.lock is
+    /// missing or out of date.
+    pub fn resolve_with(&self, features: &FeatureSelection, locked: bool) -> Result<ProjectGraph, ClinkError> {
+        let graph = try!(self.resolve_read_only(features, locked));
+        try!(graph.update_lockfile(self.lock_path(), locked));
+
+        Ok(graph)
+    }
+
+    /// Resolve like `resolve_with`, but never write Clink.lock, for commands that only show the
+    /// graph. If locked, it still fails if Clink.lock is missing or out of date.
+    pub fn resolve_read_only(&self, features: &FeatureSelection, locked: bool) -> Result<ProjectGraph, ClinkError> {
+        // Fail before anything is fetched
+        let lock_path = self.lock_path();
+        if locked && !lock_path.exists() {
+            return Err(ClinkError::InvalidProjectStructure(
+                lock_path, "Clink.lock does not exist, but --locked was passed".into()
+            ));
+        }
+
+        let graph = try!(ProjectGraph::resolve_workspace(self, features));
+        if locked && try!(graph.lockfile_changed(&lock_path)) {
+            return Err(ClinkError::InvalidProjectStructure(
+                lock_path, "Clink.lock needs to be updated, but --locked was passed".into()
+            ));
+        }
+
+        Ok(graph)
+    }
+
+    /// The path of the workspace's Clink.lock.
+    pub fn lock_path(&self) -> PathBuf {
+        files::clone_push_path(&self.path, "Clink.lock")
+    }
+
+    /// Check if resolving a graph would change Clink.lock.
+    pub fn lockfile_changed(&self, graph: &ProjectGraph) -> Result<bool, ClinkError> {
+        graph.lockfile_changed(self.lock_path())
+    }
+
+    /// Generate one Visual Studio solution holding all members and their dependencies.
+    pub fn generate_sln(&self) -> Result<(), ClinkError> {
+        let graph = try!(self.resolve());
+        VisualStudioGenerator::new().generate(&graph)
+    }
+
+    /// Generate the solution like `generate_sln`, requiring Clink.lock to be up to date.
+    pub fn generate_sln_locked(&self) -> Result<(), ClinkError> {
+        let graph = try!(self.resolve_locked());
+        VisualStudioGenerator::new().generate(&graph)
+    }
+}
+
+/// Find the root folder of the workspace a folder belongs to. This is the nearest project in the
+/// folder or one of its parents, or the first workspace above that project if it's one of its
+/// members. Only the manifests of the workspace and the project are read, so a broken member
+/// doesn't stop the root from being found.
+pub fn find_workspace_root<P: AsRef<Path>>(start: P) -> Result<PathBuf, ClinkError> {
+    let project_path = try!(find_project_path(start));
+
+    // A workspace manifest is a root of its own, if it can't be read there's nothing to join
+    let is_workspace = project::read_manifest(&project_path).map(|t| t.contains_key("workspace")).unwrap_or(false);
+    if is_workspace {
+        return Ok(project_path);
+    }
+
+    for ancestor in project_path.ancestors().skip(1) {
+        let ancestor = ancestor.to_path_buf();
+        if !files::clone_push_path(&ancestor, "Clink.toml").is_file() {
+            continue;
+        }
+
+        let toml = try!(project::read_manifest(&ancestor));
+        if let Some(workspace) = toml.get("workspace") {
+            let members = try!(match_members(&ancestor, try!(toml_table(workspace, "workspace"))));
+            if members.iter().any(|m| wincanonicalize(m).map(|m| m == project_path).unwrap_or(false)) {
+                return Ok(ancestor);
+            }
+
+            // Like cargo, only the first workspace up the tree counts
+            break;
+        }
+    }
+
+    Ok(project_path)
+}
+
+/// Find the project folders matching a [workspace] table's member patterns, leaving out the
+/// folders in its exclude list. Every pattern needs to match at least one project.
+pub fn match_members(root: &Path, workspace: &Table) -> Result<Vec<PathBuf>, ClinkError> {
+    let patterns = if workspace.contains_key("members") {
+        try!(toml_value_str_array(workspace, "members"))
+    } else {
+        Vec::new()
+    };
+    let excluded: Vec<PathBuf> = if workspace.contains_key("exclude") {
+        try!(toml_value_str_array(workspace, "exclude")).iter().map(|e| root.join(e)).collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut members = Vec::new();
+    for pattern in patterns {
+        let full_pattern = root.join(&pattern);
+        let paths = try!(glob(&full_pattern.to_string_lossy()).map_err(|e|
+            ClinkError::InvalidProjectFile(format!("\"{}\" is not a valid member pattern: {}", pattern, e))
+        ));
+
+        // Patterns may match other files next to member folders
+        let matched: Vec<PathBuf> = paths.filter_map(|p| p.ok())
+            .filter(|p| p.join("Clink.toml").is_file())
+            .collect();
+        if matched.is_empty() {
+            return Err(ClinkError::InvalidProjectStructure(
+                root.to_path_buf(), format!("Workspace member \"{}\" did not match any projects", pattern)
+            ));
+        }
+
+        members.extend(matched.into_iter().filter(|m| !excluded.iter().any(|e| m.starts_with(e))));
+    }
+
+    Ok(members)
+}
+
+/// Find the folder of the nearest Clink.toml, in the given folder or one of its parents.
+pub fn find_project_path<P: AsRef<Path>>(start: P) -> Result<PathBuf, ClinkError> {
+    let start = start.as_ref();
+    let canonical = try!(wincanonicalize(start).map_err(|_|
+        ClinkError::InvalidProjectStructure(start.to_path_buf(), "Folder does not exist".into())
+    ));
+
+    canonical.ancestors()
+        .find(|p| p.join("Clink.toml").is_file())
+        .map(|p| p.to_path_buf())
+        .ok_or_else(|| ClinkError::InvalidProjectStructure(
+            canonical.clone(), "Could not find Clink.toml in this folder or any parent folder".into()
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::Path;
+    use check::check_manifests;
+    use features::FeatureSelection;
+    use files::TempDir;
+    use graph::ProjectGraph;
+    use super::{Workspace, find_workspace_root};
+
+    fn write_manifest(folder: &Path, manifest: &str) {
+        fs::create_dir_all(folder).unwrap();
+        File::create(folder.join("Clink.toml")).unwrap().write_all(manifest.as_bytes()).unwrap();
+    }
+
+    /// A workspace with apps/App, apps/Old (excluded), apps/docs (no project), libs/Math and a
+    /// tools/Tool project that isn't a member.
+    fn write_workspace(name: &str) -> TempDir {
+        let dir = TempDir::new(&format!("workspace-{}", name));
+        let root = dir.path();
+        write_manifest(root, concat!(
+            "[workspace]\nmembers = [\"apps/*\", \"libs/*\"]\nexclude = [\"apps/Old\"]\n",
+            "[workspace.build]\ndefines = [\"STUDIO\"]\n",
+        ));
+        write_manifest(&root.join("apps").join("App"), concat!(
+            "[package]\nname = \"App\"\ntype = \"application\"\n",
+            "[dependencies]\nMath = \"../../libs/Math\"\n[build]\ndefines = [\"APP\"]\n",
+        ));
+        write_manifest(&root.join("apps").join("Old"), "[package]\nname = \"Old\"\ntype = \"application\"\n");
+        fs::create_dir_all(root.join("apps").join("docs")).unwrap();
+        write_manifest(&root.join("libs").join("Math"), "[package]\nname = \"Math\"\ntype = \"library\"\n");
+        write_manifest(&root.join("tools").join("Tool"), "[package]\nname = \"Tool\"\ntype = \"application\"\n");
+        dir
+    }
+
+    #[test]
+    fn open_matches_member_globs_except_excluded() {
+        let dir = write_workspace("globs");
+        let workspace = Workspace::open(dir.path()).unwrap();
+
+        let mut names: Vec<&str> = workspace.members().iter().map(|m| m.name().as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!("App", "Math"));
+    }
+
+    #[test]
+    fn members_inherit_workspace_settings() {
+        let dir = write_workspace("settings");
+        let workspace = Workspace::open(dir.path()).unwrap();
+        let graph = ProjectGraph::resolve_workspace(&workspace, &FeatureSelection::new()).unwrap();
+
+        assert_eq!(graph.find("App").unwrap().settings.defines, vec!("STUDIO", "APP"));
+        assert_eq!(graph.find("Math").unwrap().settings.defines, vec!("STUDIO"));
+    }
+
+    #[test]
+    fn resolve_read_only_leaves_lockfile_alone() {
+        let dir = write_workspace("read-only");
+        let workspace = Workspace::open(dir.path()).unwrap();
+        let features = FeatureSelection::new();
+
+        let graph = workspace.resolve_read_only(&features, false).unwrap();
+        assert!(graph.find("App").is_some());
+        assert!(!workspace.lock_path().exists());
+        assert!(workspace.resolve_read_only(&features, true).is_err());
+
+        workspace.resolve().unwrap();
+        let lock = fs::read(workspace.lock_path()).unwrap();
+        workspace.resolve_read_only(&features, true).unwrap();
+        assert_eq!(fs::read(workspace.lock_path()).unwrap(), lock);
+    }
+
+    #[test]
+    fn discover_only_joins_workspaces_it_is_a_member_of() {
+        let dir = write_workspace("discover");
+        let src = dir.path().join("apps").join("App").join("src");
+        fs::create_dir_all(&src).unwrap();
+
+        let from_member = Workspace::discover(&src).unwrap();
+        let from_outsider = find_workspace_root(dir.path().join("tools").join("Tool")).unwrap();
+        let outsider_check = check_manifests(dir.path().join("tools").join("Tool")).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+
+        assert_eq!(from_member.path(), &root);
+        assert_eq!(from_member.members().len(), 2);
+        assert_eq!(from_outsider, root.join("tools").join("Tool"));
+        assert_eq!(outsider_check.manifests, vec!(root.join("tools").join("Tool").join("Clink.toml")));
+    }
+}
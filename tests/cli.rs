@@ -0,0 +1,133 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A project folder in the system's temporary folder, removed again when dropped.
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        let path = env::temp_dir().join(format!("clink-cli-{}-test-{}", name, ::std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+fn write_file(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    File::create(path).unwrap().write_all(contents.as_bytes()).unwrap();
+}
+
+fn clink(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_clink")).args(args).current_dir(dir).output().unwrap()
+}
+
+/// Prints its arguments and exits with the amount of them.
+const ECHO: &str = "#include <cstdio>\n\
+    int main(int argc, char** argv) {\n\
+    for (int i = 1; i < argc; i++) std::printf(\"[%s]\", argv[i]);\n\
+    return argc - 1;\n\
+    }\n";
+
+#[test]
+fn run_picks_the_only_application_and_passes_arguments() {
+    let dir = TempDir::new("run");
+    write_file(&dir.path.join("Clink.toml"), "[package]\nname = \"Tools\"\ntype = \"library\"\n");
+    write_file(&dir.path.join("src").join("bin").join("echo.cpp"), ECHO);
+
+    let output = clink(&dir.path, &["run", "--", "one", "--two"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[one][--two]"), "{}", stdout);
+    assert_eq!(output.status.code(), Some(2));
+
+    // With a second binary it has to be picked by name
+    write_file(&dir.path.join("src").join("bin").join("other.cpp"), "int main() { return 0; }\n");
+    let output = clink(&dir.path, &["run"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("There are several applications, specify one with --bin: echo, other"), "{}", stderr);
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = clink(&dir.path, &["run", "--bin", "echo", "--", "three"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("[three]"));
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(clink(&dir.path, &["run", "--bin", "other"]).status.code(), Some(0));
+}
+
+#[test]
+fn test_fails_if_any_test_fails() {
+    let dir = TempDir::new("test");
+    write_file(&dir.path.join("Clink.toml"), "[package]\nname = \"Math\"\ntype = \"library\"\n");
+    write_file(&dir.path.join("tests").join("passes.cpp"), "int main() { return 0; }\n");
+
+    let output = clink(&dir.path, &["test"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Math-passes ... ok"), "{}", stdout);
+    assert!(stdout.contains("test result: ok. 1 passed; 0 failed"), "{}", stdout);
+    assert!(output.status.success());
+
+    write_file(&dir.path.join("tests").join("fails.cpp"), "int main() { return 3; }\n");
+    let output = clink(&dir.path, &["test"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Math-fails ... FAILED"), "{}", stdout);
+    assert!(stdout.contains("Math-passes ... ok"), "{}", stdout);
+    assert!(stdout.contains("test result: FAILED. 1 passed; 1 failed"), "{}", stdout);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn run_counts_the_application_and_its_binaries_but_not_examples() {
+    let dir = TempDir::new("run-default");
+    write_file(&dir.path.join("Clink.toml"), "[package]\nname = \"App\"\ntype = \"application\"\n");
+    write_file(&dir.path.join("src").join("main.cpp"), ECHO);
+    write_file(&dir.path.join("examples").join("demo.cpp"), "int main() { return 0; }\n");
+
+    let output = clink(&dir.path, &["run", "--", "x"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("[x]"));
+    assert_eq!(output.status.code(), Some(1));
+
+    write_file(&dir.path.join("Clink.toml"), concat!(
+        "[package]\nname = \"App\"\ntype = \"application\"\n",
+        "[[bin]]\nname = \"tool\"\n",
+    ));
+    write_file(&dir.path.join("src").join("bin").join("tool.cpp"), "int main() { return 0; }\n");
+    let output = clink(&dir.path, &["run"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("There are several applications, specify one with --bin: App, tool"), "{}", stderr);
+    assert_eq!(clink(&dir.path, &["run", "--bin", "tool"]).status.code(), Some(0));
+}
+
+#[test]
+fn generate_check_fails_on_missing_and_stale_files() {
+    let dir = TempDir::new("generate-check");
+    write_file(&dir.path.join("Clink.toml"), "[package]\nname = \"Game\"\ntype = \"application\"\n");
+    write_file(&dir.path.join("src").join("main.cpp"), "int main() { return 0; }\n");
+
+    let output = clink(&dir.path, &["generate", "--check"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("Missing {}", dir.path.join("Game.sln").display())), "{}", stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!dir.path.join("Game.sln").exists());
+
+    assert!(clink(&dir.path, &["generate"]).status.success());
+    let output = clink(&dir.path, &["generate", "--check"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("all up to date"));
+    assert!(output.status.success());
+
+    write_file(&dir.path.join("Game.vcxproj"), "edited by hand\n");
+    let output = clink(&dir.path, &["generate", "--check"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("Stale {}", dir.path.join("Game.vcxproj").display())), "{}", stderr);
+    assert!(!stderr.contains("Game.sln"), "{}", stderr);
+    assert_eq!(output.status.code(), Some(1));
+}